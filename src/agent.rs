@@ -1,47 +1,333 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use rand_distr::{Distribution, WeightedAliasIndex};
+use rand::{seq::SliceRandom, Rng};
+use rand_distr::{Distribution, Normal, WeightedAliasIndex};
 use serde::{Deserialize, Serialize};
 use strum::Display;
 
+const IMAGE_SCORE_MAX: i32 = 5;
+
+#[derive(Clone, Copy, Serialize, Display, Debug, clap::ValueEnum, PartialEq, Eq, Deserialize)]
+pub enum ReciprocityModel {
+    None,
+    GrimTrigger,
+    TitForTat,
+    WinStayLoseShift,
+}
+
+#[derive(Clone, Copy, Serialize, Display, Debug, clap::ValueEnum, PartialEq, Eq, Deserialize)]
+pub enum ReputationModel {
+    None,
+    ImageScoring,
+    SternJudging,
+}
+
 #[derive(Clone, Copy, Serialize, Display, Debug, clap::ValueEnum, PartialEq, Eq, Deserialize)]
 pub enum ResourceDistributionModel {
     Uniform,
 }
 
+#[derive(Clone, Copy, Serialize, Display, Debug, clap::ValueEnum, PartialEq, Eq, Deserialize)]
+pub enum ConquestModel {
+    None,
+    Assimilation,
+    Edge,
+}
+
+#[derive(Clone, Copy, Serialize, Display, Debug, clap::ValueEnum, PartialEq, Eq, Deserialize)]
+pub enum ContestModel {
+    Multiparty,
+    Pairwise,
+}
+
+#[derive(Clone, Copy, Serialize, Display, Debug, clap::ValueEnum, PartialEq, Eq, Deserialize)]
+pub enum EngineModel {
+    Agent,
+    Gillespie,
+    MeanField,
+    PairApproximation,
+}
+
+#[derive(Clone, Copy, Serialize, Display, Debug, clap::ValueEnum, PartialEq, Eq, Deserialize)]
+pub enum FightModel {
+    ExpectedValue,
+    Investment,
+    Plunder,
+}
+
+#[derive(Clone, Copy, Serialize, Display, Debug, clap::ValueEnum, PartialEq, Eq, Deserialize)]
+pub enum FitnessModel {
+    Cumulative,
+    DegreeNormalized,
+    Discounted,
+    Instant,
+    Window,
+}
+
+#[derive(Clone, Copy, Serialize, Display, Debug, clap::ValueEnum, PartialEq, Eq, Deserialize)]
+pub enum NetworkModel {
+    Quenched,
+    Annealed,
+    AnnealedMixed,
+    WellMixed,
+}
+
+#[derive(Clone, Copy, Serialize, Display, Debug, clap::ValueEnum, PartialEq, Eq, Deserialize)]
+pub enum TributeModel {
+    None,
+    Always,
+    Rational,
+    Trait,
+}
+
+#[derive(Clone, Copy, Serialize, Display, Debug, clap::ValueEnum, PartialEq, Eq, Deserialize)]
+pub enum ZealotModel {
+    None,
+    File,
+    Hubs,
+    Random,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Action {
+    Attack,
+    Cooperate,
+    Defect,
+}
+
+/// What an agent remembers about one partner.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct EdgeMemory {
+    pub action_last: Action,
+    pub flag_betrayed: bool,
+}
+
+#[derive(Clone, Copy, Serialize, Display, Debug, clap::ValueEnum, PartialEq, Eq, Deserialize)]
+pub enum StateModel {
+    None,
+    Equal,
+    Police,
+    Subsidy,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug, clap::ValueEnum)]
 pub enum Strategy {
     Cooperator,
     Defector,
     Fighter,
 }
 
+/// Probability vector over cooperating, defecting and fighting that plays `strategy` for sure.
+pub fn mixed_pure(strategy: Strategy) -> [f64; 3] {
+    let mut mixed = [0.0; 3];
+    mixed[strategy as usize] = 1.0;
+    mixed
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Agent {
+    pub flag_payer: bool,
+    pub flag_payer_temp: bool,
+    pub flag_zealot: bool,
     pub id: usize,
+    pub image_score: i32,
+    pub military_stock: f64,
+    pub mixed: [f64; 3],
+    pub mixed_temp: [f64; 3],
     pub neighbors: Vec<usize>,
+    pub neighbors_in: Vec<usize>,
+    pub payoff_discounted: f64,
+    pub payoff_window: VecDeque<f64>,
     pub resources_cumulative: f64,
     pub resources_initial: f64,
     pub resources_instant: f64,
     pub strategy: Strategy,
     pub strategy_temp: Strategy,
+    pub technology: f64,
+    pub technology_temp: f64,
+    pub territory: usize,
+    pub weights: Vec<f64>,
+    pub weights_in: Vec<f64>,
 }
 
 impl Agent {
     pub fn new(
         id: usize,
         neighbors: Vec<usize>,
+        weights: Vec<f64>,
         resources_initial: f64,
         strategy: Strategy,
     ) -> Self {
         Self {
+            flag_payer: false,
+            flag_payer_temp: false,
+            flag_zealot: false,
             id,
+            image_score: 0,
+            military_stock: 0.0,
+            mixed: mixed_pure(strategy),
+            mixed_temp: mixed_pure(strategy),
+            neighbors_in: neighbors.clone(),
             neighbors,
+            payoff_discounted: 0.0,
+            payoff_window: VecDeque::new(),
             resources_cumulative: resources_initial,
             resources_initial,
             resources_instant: 0.0,
             strategy,
             strategy_temp: strategy,
+            technology: 1.0,
+            technology_temp: 1.0,
+            territory: 0,
+            weights_in: weights.clone(),
+            weights,
+        }
+    }
+
+    pub fn strength(&self) -> f64 {
+        self.weights.iter().sum()
+    }
+
+    /// Payoff measure compared in the Fermi imitation step.
+    pub fn fitness(&self, model_fitness: FitnessModel) -> f64 {
+        match model_fitness {
+            FitnessModel::Cumulative => self.resources_cumulative,
+            FitnessModel::DegreeNormalized => {
+                self.resources_instant / self.neighbors.len().max(1) as f64
+            }
+            FitnessModel::Discounted => self.payoff_discounted,
+            FitnessModel::Instant => self.resources_instant,
+            FitnessModel::Window => self.payoff_window.iter().sum(),
+        }
+    }
+
+    /// Public reputation read by discriminating cooperators.
+    pub fn flag_good(&self) -> bool {
+        self.image_score >= 0
+    }
+
+    /// Updates the image after the agent played `action` towards a recipient. Image scoring
+    /// counts cooperations up and defections down; stern judging rates as good helping good
+    /// recipients and refusing bad ones. Attacking a cooperator is bad under both norms.
+    pub fn assess(
+        &mut self,
+        action: Action,
+        strategy_recipient: Strategy,
+        flag_good_recipient: bool,
+        model_reputation: ReputationModel,
+    ) {
+        self.image_score = match (model_reputation, action) {
+            (ReputationModel::None, _) => return,
+            (_, Action::Attack) if strategy_recipient != Strategy::Cooperator => return,
+            (ReputationModel::ImageScoring, Action::Cooperate) => self.image_score + 1,
+            (ReputationModel::ImageScoring, _) => self.image_score - 1,
+            (ReputationModel::SternJudging, Action::Attack) => -1,
+            (ReputationModel::SternJudging, _) => {
+                if (action == Action::Cooperate) == flag_good_recipient {
+                    1
+                } else {
+                    -1
+                }
+            }
+        }
+        .clamp(-IMAGE_SCORE_MAX, IMAGE_SCORE_MAX);
+    }
+
+    /// Draws the pure strategy played this step from the mixed strategy.
+    pub fn sample_mixed<R: Rng>(&mut self, rng: &mut R) {
+        let trial: f64 = rng.gen();
+        let mut cumulative = 0.0;
+        for (strategy, probability) in [Strategy::Cooperator, Strategy::Defector, Strategy::Fighter]
+            .into_iter()
+            .zip(self.mixed)
+        {
+            cumulative += probability;
+            if trial < cumulative {
+                self.strategy = strategy;
+                return;
+            }
+        }
+        self.strategy = Strategy::Fighter;
+    }
+
+    /// Mixed strategy moved a `rate_blend` step towards `mixed_model`, so 1 copies the model,
+    /// then perturbed by Gaussian noise of width `parameter_mutation` and projected back onto
+    /// the simplex.
+    pub fn blend_mixed<R: Rng>(
+        &self,
+        mixed_model: &[f64; 3],
+        rate_blend: f64,
+        parameter_mutation: f64,
+        rng: &mut R,
+    ) -> [f64; 3] {
+        let mut mixed = [0.0; 3];
+        for (i, probability) in mixed.iter_mut().enumerate() {
+            *probability = (1.0 - rate_blend) * self.mixed[i] + rate_blend * mixed_model[i];
+        }
+
+        if parameter_mutation > 0.0 {
+            let noise = Normal::new(0.0, parameter_mutation).unwrap();
+            for probability in mixed.iter_mut() {
+                *probability = (*probability + noise.sample(rng)).max(0.0);
+            }
+        }
+
+        let total: f64 = mixed.iter().sum();
+        if total > 0.0 {
+            mixed
+                .iter_mut()
+                .for_each(|probability| *probability /= total);
+            mixed
+        } else {
+            self.mixed
+        }
+    }
+
+    /// Resources available for fighting: the `fraction_investment` share of the military stock
+    /// when it is modeled, otherwise of current resources.
+    pub fn war_chest(&self, fraction_investment: f64, flag_military_stock: bool) -> f64 {
+        if flag_military_stock {
+            fraction_investment * self.military_stock
+        } else {
+            fraction_investment * self.resources_cumulative
+        }
+    }
+
+    /// Moves resources into the military stock at `rate_armament` per unit time over an interval
+    /// `dt`, while the stock depreciates at `rate_depreciation`.
+    pub fn arm(&mut self, rate_armament: f64, rate_depreciation: f64, dt: f64) {
+        let investment = (1.0 - (1.0 - rate_armament).powf(dt)) * self.resources_cumulative;
+        self.military_stock = (1.0 - rate_depreciation).powf(dt) * self.military_stock + investment;
+        self.resources_cumulative -= investment;
+    }
+
+    /// Contest effectiveness multiplying the war resources entering the CSF; 1 unless technology
+    /// is modeled.
+    pub fn effectiveness(&self, flag_technology: bool) -> f64 {
+        if flag_technology {
+            self.technology
+        } else {
+            1.0
+        }
+    }
+
+    /// Spends a `rate_research` share of resources on R&D, raising technology by
+    /// `parameter_research` per unit invested.
+    pub fn research(&mut self, rate_research: f64, parameter_research: f64) {
+        let investment = rate_research * self.resources_cumulative.max(0.0);
+        self.technology += parameter_research * investment;
+        self.technology_temp = self.technology;
+        self.resources_cumulative -= investment;
+    }
+
+    /// Folds the payoff of the last interaction round into the discounted and windowed payoffs.
+    pub fn record_payoff(&mut self, rate_discount: f64, t_window: usize) {
+        self.payoff_discounted =
+            (1.0 - rate_discount) * self.payoff_discounted + self.resources_instant;
+
+        self.payoff_window.push_back(self.resources_instant);
+        while self.payoff_window.len() > t_window {
+            self.payoff_window.pop_front();
         }
     }
 }
@@ -49,14 +335,19 @@ impl Agent {
 #[derive(Serialize, Deserialize, Clone)]
 pub struct AgentEnsemble {
     inner: Vec<Agent>,
+    #[serde(skip)]
+    isolated: HashSet<usize>,
+    #[serde(skip)]
+    memory: HashMap<(usize, usize), EdgeMemory>,
 }
 
 impl AgentEnsemble {
     pub fn new(
-        adjacency_list: &HashMap<usize, Vec<usize>>,
+        adjacency_list: &HashMap<usize, Vec<(usize, f64)>>,
         fraction_cooperators: f64,
         fraction_defectors: f64,
         _model_distribution_resources: ResourceDistributionModel,
+        flag_directed: bool,
     ) -> Self {
         let mut rng = rand::thread_rng();
 
@@ -68,8 +359,6 @@ impl AgentEnsemble {
         let dist = WeightedAliasIndex::new(weights).unwrap();
 
         for id in 0..nagents {
-            let neighbors = adjacency_list.get(&id).unwrap().clone();
-
             let resources_init = 1.0;
 
             let strategy = match dist.sample(&mut rng) {
@@ -78,11 +367,303 @@ impl AgentEnsemble {
                 _ => Strategy::Fighter,
             };
 
-            let agent = Agent::new(id, neighbors, resources_init, strategy);
+            let agent = Agent::new(id, Vec::new(), Vec::new(), resources_init, strategy);
             list_agents.push(agent);
         }
 
-        AgentEnsemble { inner: list_agents }
+        let mut agent_ensemble = AgentEnsemble {
+            inner: list_agents,
+            isolated: HashSet::new(),
+            memory: HashMap::new(),
+        };
+        agent_ensemble.set_network(adjacency_list, flag_directed);
+
+        agent_ensemble
+    }
+
+    /// Loads the edges of `adjacency_list`. Agents isolated by an intervention stay isolated.
+    pub fn set_network(
+        &mut self,
+        adjacency_list: &HashMap<usize, Vec<(usize, f64)>>,
+        flag_directed: bool,
+    ) {
+        for agent in self.inner.iter_mut() {
+            let (neighbors, weights): (Vec<usize>, Vec<f64>) = adjacency_list
+                .get(&agent.id)
+                .unwrap()
+                .iter()
+                .cloned()
+                .unzip();
+            agent.neighbors_in = neighbors.clone();
+            agent.weights_in = weights.clone();
+            agent.neighbors = neighbors;
+            agent.weights = weights;
+        }
+
+        if flag_directed {
+            for agent in self.inner.iter_mut() {
+                agent.neighbors_in.clear();
+                agent.weights_in.clear();
+            }
+            for id in 0..self.inner.len() {
+                for (neighbor, weight) in adjacency_list.get(&id).unwrap() {
+                    self.inner[*neighbor].neighbors_in.push(id);
+                    self.inner[*neighbor].weights_in.push(*weight);
+                }
+            }
+        }
+
+        self.cut_isolated();
+    }
+
+    /// Redraws every agent's partners. `Annealed` matches stubs of the given degree sequence at
+    /// random, `AnnealedMixed` draws as many edges as the sequence holds between uniformly
+    /// chosen pairs. Self-loops and repeated edges are dropped and weights are reset to 1.
+    pub fn rewire_annealed<R: Rng>(
+        &mut self,
+        degree_sequence: &[usize],
+        model_network: NetworkModel,
+        rng: &mut R,
+    ) {
+        let nagents = self.inner.len();
+        let mut adjacency_list: HashMap<usize, Vec<(usize, f64)>> =
+            (0..nagents).map(|id| (id, Vec::new())).collect();
+        let mut edges = HashSet::new();
+
+        let mut add_edge = |source: usize, target: usize| {
+            let edge = (source.min(target), source.max(target));
+            if source != target && edges.insert(edge) {
+                adjacency_list.get_mut(&source).unwrap().push((target, 1.0));
+                adjacency_list.get_mut(&target).unwrap().push((source, 1.0));
+            }
+        };
+
+        match model_network {
+            NetworkModel::AnnealedMixed => {
+                let nedges = degree_sequence.iter().sum::<usize>() / 2;
+                for _ in 0..nedges {
+                    add_edge(rng.gen_range(0..nagents), rng.gen_range(0..nagents));
+                }
+            }
+            _ => {
+                let mut stubs: Vec<usize> = degree_sequence
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(id, degree)| std::iter::repeat_n(id, *degree))
+                    .collect();
+                stubs.shuffle(rng);
+                for pair in stubs.chunks_exact(2) {
+                    add_edge(pair[0], pair[1]);
+                }
+            }
+        }
+
+        self.set_network(&adjacency_list, false);
+    }
+
+    /// Commits the given agents to `strategy`; they never revise it by imitation.
+    pub fn assign_zealots(&mut self, ids: &[usize], strategy: Strategy) {
+        for id in ids {
+            let agent = &mut self.inner[*id];
+            agent.flag_zealot = true;
+            agent.mixed = mixed_pure(strategy);
+            agent.mixed_temp = agent.mixed;
+            agent.strategy = strategy;
+            agent.strategy_temp = strategy;
+        }
+    }
+
+    /// Gives each agent the heritable trait of paying tribute with probability `fraction_payers`.
+    pub fn assign_payers<R: Rng>(&mut self, fraction_payers: f64, rng: &mut R) {
+        for agent in self.inner.iter_mut() {
+            agent.flag_payer = rng.gen::<f64>() < fraction_payers;
+            agent.flag_payer_temp = agent.flag_payer;
+        }
+    }
+
+    /// Switches the given agents to `strategy_new`, restricted to those currently playing
+    /// `strategy_old` when it is given. Zealots and ids outside the population are skipped.
+    pub fn convert_strategy(
+        &mut self,
+        ids: &[usize],
+        strategy_old: Option<Strategy>,
+        strategy_new: Strategy,
+    ) {
+        for id in ids {
+            let Some(agent) = self.inner.get_mut(*id) else {
+                continue;
+            };
+            if !agent.flag_zealot && strategy_old.is_none_or(|strategy| agent.strategy == strategy)
+            {
+                agent.mixed = mixed_pure(strategy_new);
+                agent.mixed_temp = agent.mixed;
+                agent.strategy = strategy_new;
+                agent.strategy_temp = strategy_new;
+            }
+        }
+    }
+
+    /// Adds `amount` to the resources of every agent playing `strategy`.
+    pub fn inject_resources(&mut self, strategy: Strategy, amount: f64) {
+        for agent in self.inner.iter_mut() {
+            if agent.strategy == strategy {
+                agent.resources_cumulative += amount;
+            }
+        }
+    }
+
+    /// Cuts every edge, incoming and outgoing, of the given agents, now and whenever the network
+    /// is loaded again.
+    pub fn isolate(&mut self, ids: &[usize]) {
+        self.isolated.extend(ids.iter().copied());
+        self.cut_isolated();
+    }
+
+    fn cut_isolated(&mut self) {
+        if self.isolated.is_empty() {
+            return;
+        }
+
+        let isolated = &self.isolated;
+        let retain = |neighbors: &mut Vec<usize>, weights: &mut Vec<f64>| {
+            let (neighbors_kept, weights_kept) = neighbors
+                .iter()
+                .zip(weights.iter())
+                .filter(|(neighbor, _)| !isolated.contains(neighbor))
+                .unzip();
+            *neighbors = neighbors_kept;
+            *weights = weights_kept;
+        };
+
+        for agent in self.inner.iter_mut() {
+            if isolated.contains(&agent.id) {
+                agent.neighbors.clear();
+                agent.weights.clear();
+                agent.neighbors_in.clear();
+                agent.weights_in.clear();
+            } else {
+                retain(&mut agent.neighbors, &mut agent.weights);
+                retain(&mut agent.neighbors_in, &mut agent.weights_in);
+            }
+        }
+    }
+
+    /// Hands the edge between `loser` and `winner` over to the winner: the loser's link to the
+    /// winner is cut and the winner keeps, or gains, its link to the loser. Only meant for
+    /// directed networks. Returns false when the loser has no link to the winner.
+    pub fn seize_edge(&mut self, loser: usize, winner: usize) -> bool {
+        let index = match self.inner[loser]
+            .neighbors
+            .iter()
+            .position(|n| *n == winner)
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        let weight = self.inner[loser].weights[index];
+
+        self.unlink(loser, winner);
+        if !self.inner[winner].neighbors.contains(&loser) {
+            self.link(winner, loser, weight);
+        }
+
+        true
+    }
+
+    fn link(&mut self, source: usize, target: usize, weight: f64) {
+        self.inner[source].neighbors.push(target);
+        self.inner[source].weights.push(weight);
+        self.inner[target].neighbors_in.push(source);
+        self.inner[target].weights_in.push(weight);
+    }
+
+    fn unlink(&mut self, source: usize, target: usize) {
+        if let Some(index) = self.inner[source]
+            .neighbors
+            .iter()
+            .position(|n| *n == target)
+        {
+            self.inner[source].neighbors.remove(index);
+            self.inner[source].weights.remove(index);
+        }
+        if let Some(index) = self.inner[target]
+            .neighbors_in
+            .iter()
+            .position(|n| *n == source)
+        {
+            self.inner[target].neighbors_in.remove(index);
+            self.inner[target].weights_in.remove(index);
+        }
+    }
+
+    /// Action of `focal` towards `partner` in the dilemma. Fighters attack and defectors defect;
+    /// cooperators refuse partners with a bad reputation when reputations are modeled, and
+    /// otherwise follow the reactive rule on their memory of the partner, opening with
+    /// cooperation.
+    pub fn action(
+        &self,
+        focal: usize,
+        partner: usize,
+        model_reciprocity: ReciprocityModel,
+        model_reputation: ReputationModel,
+    ) -> Action {
+        match self.inner[focal].strategy {
+            Strategy::Fighter => return Action::Attack,
+            Strategy::Defector => return Action::Defect,
+            Strategy::Cooperator => {}
+        }
+
+        if model_reputation != ReputationModel::None && !self.inner[partner].flag_good() {
+            return Action::Defect;
+        }
+
+        let memory = match self.memory.get(&(focal, partner)) {
+            Some(memory) => memory,
+            None => return Action::Cooperate,
+        };
+
+        match model_reciprocity {
+            ReciprocityModel::None => Action::Cooperate,
+            ReciprocityModel::GrimTrigger => {
+                if memory.flag_betrayed {
+                    Action::Defect
+                } else {
+                    Action::Cooperate
+                }
+            }
+            ReciprocityModel::TitForTat => {
+                if memory.action_last == Action::Cooperate {
+                    Action::Cooperate
+                } else {
+                    Action::Defect
+                }
+            }
+            ReciprocityModel::WinStayLoseShift => {
+                let action_own = self
+                    .memory
+                    .get(&(partner, focal))
+                    .map_or(Action::Cooperate, |memory| memory.action_last);
+                let flag_win = memory.action_last == Action::Cooperate;
+                if (action_own == Action::Cooperate) == flag_win {
+                    Action::Cooperate
+                } else {
+                    Action::Defect
+                }
+            }
+        }
+    }
+
+    /// Records that `partner` played `action` against `observer`.
+    pub fn remember(&mut self, observer: usize, partner: usize, action: Action) {
+        let memory = self
+            .memory
+            .entry((observer, partner))
+            .or_insert(EdgeMemory {
+                action_last: action,
+                flag_betrayed: false,
+            });
+        memory.action_last = action;
+        memory.flag_betrayed |= action != Action::Cooperate;
     }
 
     pub fn inner(&self) -> &Vec<Agent> {
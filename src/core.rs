@@ -1,18 +1,79 @@
-use std::{collections::HashSet, env, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    path::PathBuf,
+};
 
-use rand::Rng;
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    seq::index::sample,
+    Rng,
+};
 
 use crate::{
-    agent::{AgentEnsemble, Strategy},
+    agent::{
+        Action, Agent, AgentEnsemble, ConquestModel, ContestModel, EngineModel, FightModel,
+        NetworkModel, ReciprocityModel, ReputationModel, StateModel, Strategy, TributeModel,
+        ZealotModel,
+    },
+    gillespie::gillespie_loop,
     utils::{
-        assemble_events, assemble_global, construct_string_game, get_string_network, load_network,
-        save_global_results, save_to_json, summary_stats_output, FightingEvent, Input, Output,
-        OutputGlobal, TimeSeries,
+        apply_schedules, assemble_agents, assemble_contests, assemble_events, assemble_global,
+        assemble_territory, assemble_tributes, construct_string_game, get_string_network,
+        load_interventions, load_network, load_network_temporal, load_node_list, load_schedules,
+        save_global_results, save_to_json, summary_stats_output, AgentSnapshot, ContestEvent,
+        ContestParticipant, FightingEvent, Input, Intervention, InterventionAction, Output,
+        OutputGlobal, OutputMetadata, ParameterSchedule, TemporalNetwork, TimeSeries, TributeEvent,
     },
 };
 
 pub fn model_cooperation_and_fight(pars_model: &Input, path_network: &PathBuf) {
-    let adjacency_list = load_network(path_network);
+    let temporal_network = if pars_model.flag_temporal {
+        let temporal_network = load_network_temporal(
+            path_network,
+            pars_model.flag_directed,
+            pars_model.t_snapshot,
+        );
+        println!(
+            "Temporal network with {} snapshots",
+            temporal_network.number_of_snapshots()
+        );
+        Some(temporal_network)
+    } else {
+        None
+    };
+
+    let adjacency_list = match &temporal_network {
+        Some(temporal_network) => temporal_network.snapshots[0].clone(),
+        None if pars_model.model_network == NetworkModel::WellMixed => (0..pars_model
+            .size_population)
+            .map(|id| (id, Vec::new()))
+            .collect(),
+        None => load_network(path_network, pars_model.flag_directed),
+    };
+
+    let string_network = if pars_model.model_network == NetworkModel::WellMixed {
+        format!("wm_n{}", pars_model.size_population)
+    } else {
+        get_string_network(path_network).unwrap()
+    };
+
+    let zealots_file = if pars_model.model_zealots == ZealotModel::File {
+        load_node_list(&PathBuf::from(&pars_model.path_zealots))
+    } else {
+        Vec::new()
+    };
+
+    let schedules = if pars_model.path_schedules.is_empty() {
+        Vec::new()
+    } else {
+        load_schedules(&PathBuf::from(&pars_model.path_schedules))
+    };
+    let interventions = if pars_model.path_interventions.is_empty() {
+        Vec::new()
+    } else {
+        load_interventions(&PathBuf::from(&pars_model.path_interventions))
+    };
 
     let mut output_ensemble: Vec<Output> = Vec::new();
 
@@ -30,9 +91,25 @@ pub fn model_cooperation_and_fight(pars_model: &Input, path_network: &PathBuf) {
             pars_model.fraction_cooperators,
             pars_model.fraction_defectors,
             pars_model.model_distribution_resources,
+            pars_model.flag_directed,
         );
 
-        let output: Output = dynamical_loop(&mut agent_ensemble, pars_model);
+        let zealots = select_zealots(&agent_ensemble, &zealots_file, pars_model);
+        agent_ensemble.assign_zealots(&zealots, pars_model.strategy_zealots);
+        if pars_model.model_tribute == TributeModel::Trait {
+            agent_ensemble.assign_payers(pars_model.fraction_payers, &mut rand::thread_rng());
+        }
+
+        let output: Output = match pars_model.model_engine {
+            EngineModel::Gillespie => gillespie_loop(&mut agent_ensemble, pars_model),
+            _ => dynamical_loop(
+                &mut agent_ensemble,
+                pars_model,
+                temporal_network.as_ref(),
+                &schedules,
+                &interventions,
+            ),
+        };
 
         println!(
             "Global results: Avg cooperators={}, defectors={}, fighters={}",
@@ -51,9 +128,40 @@ pub fn model_cooperation_and_fight(pars_model: &Input, path_network: &PathBuf) {
         .join("results")
         .join("curated");
 
+    if !schedules.is_empty() || !interventions.is_empty() {
+        let header = "coopfight_metadata";
+        let string_game = format!(
+            "{}_{}_{}.json",
+            header,
+            construct_string_game(pars_model),
+            string_network
+        );
+        let path_game = path.join(string_game);
+
+        let metadata = OutputMetadata {
+            input: pars_model.clone(),
+            interventions: interventions.clone(),
+            schedules: schedules.clone(),
+        };
+        let _ = save_to_json(&metadata, &path_game);
+    }
+
+    if pars_model.flag_analysis_agent {
+        let header = "coopfight_agents";
+        let string_game = format!(
+            "{}_{}_{}.json",
+            header,
+            construct_string_game(pars_model),
+            string_network
+        );
+        let path_game = path.join(string_game);
+
+        let agent_ensemble = assemble_agents(&output_ensemble);
+        let _ = save_to_json(&agent_ensemble, &path_game);
+    }
+
     if pars_model.flag_analysis_event {
         let header = "coopfight_events";
-        let string_network = get_string_network(path_network).unwrap();
         let string_game = format!(
             "{}_{}_{}.json",
             header,
@@ -64,10 +172,51 @@ pub fn model_cooperation_and_fight(pars_model: &Input, path_network: &PathBuf) {
 
         let event_ensemble = assemble_events(&output_ensemble);
         let _ = save_to_json(&event_ensemble, &path_game);
+
+        if pars_model.model_conquest != ConquestModel::None {
+            let header = "coopfight_territory";
+            let string_game = format!(
+                "{}_{}_{}.json",
+                header,
+                construct_string_game(pars_model),
+                string_network
+            );
+            let path_game = path.join(string_game);
+
+            let territory_ensemble = assemble_territory(&output_ensemble);
+            let _ = save_to_json(&territory_ensemble, &path_game);
+        }
+
+        if pars_model.model_tribute != TributeModel::None {
+            let header = "coopfight_tributes";
+            let string_game = format!(
+                "{}_{}_{}.json",
+                header,
+                construct_string_game(pars_model),
+                string_network
+            );
+            let path_game = path.join(string_game);
+
+            let tribute_ensemble = assemble_tributes(&output_ensemble);
+            let _ = save_to_json(&tribute_ensemble, &path_game);
+        }
+
+        if pars_model.model_contest == ContestModel::Multiparty {
+            let header = "coopfight_contests";
+            let string_game = format!(
+                "{}_{}_{}.json",
+                header,
+                construct_string_game(pars_model),
+                string_network
+            );
+            let path_game = path.join(string_game);
+
+            let contest_ensemble = assemble_contests(&output_ensemble);
+            let _ = save_to_json(&contest_ensemble, &path_game);
+        }
     }
     if pars_model.flag_analysis_time {
         let header = "coopfight_time";
-        let string_network = get_string_network(path_network).unwrap();
         let string_game = format!(
             "{}_{}_{}.json",
             header,
@@ -89,7 +238,6 @@ pub fn model_cooperation_and_fight(pars_model: &Input, path_network: &PathBuf) {
         );
 
         let header = "coopfight_global";
-        let string_network = get_string_network(path_network).unwrap();
         let string_game = format!(
             "{}_{}_{}.json",
             header,
@@ -105,8 +253,15 @@ pub fn model_cooperation_and_fight(pars_model: &Input, path_network: &PathBuf) {
     println!("The game is over!");
 }
 
-pub fn dynamical_loop(agent_ensemble: &mut AgentEnsemble, pars_model: &Input) -> Output {
+pub fn dynamical_loop(
+    agent_ensemble: &mut AgentEnsemble,
+    pars_model: &Input,
+    temporal_network: Option<&TemporalNetwork>,
+    schedules: &[ParameterSchedule],
+    interventions: &[Intervention],
+) -> Output {
     let mut rng = rand::thread_rng();
+    let mut pars_scheduled = pars_model.clone();
 
     let nagents = agent_ensemble.number_of_agents();
 
@@ -119,6 +274,11 @@ pub fn dynamical_loop(agent_ensemble: &mut AgentEnsemble, pars_model: &Input) ->
 
     let mut event_ensemble: Vec<FightingEvent> = Vec::new();
     let mut event_count = 0;
+    let mut contest_ensemble: Vec<ContestEvent> = Vec::new();
+    let mut tribute_ensemble: Vec<TributeEvent> = Vec::new();
+    let mut conquests: Vec<(usize, usize)> = Vec::new();
+    let mut truces: HashMap<(usize, usize), f64> = HashMap::new();
+    let mut state_pool = 0.0;
 
     let t_equilibrium = pars_model.t_equilibrium;
     let t_average = pars_model.t_average;
@@ -130,13 +290,102 @@ pub fn dynamical_loop(agent_ensemble: &mut AgentEnsemble, pars_model: &Input) ->
     let mut time_series_payoff_cooperators = vec![0.0; t_total];
     let mut time_series_payoff_defectors = vec![0.0; t_total];
     let mut time_series_payoff_fighters = vec![0.0; t_total];
+    let mut time_series_snapshot = vec![0; t_total];
+    let mut time_series_number_zealots = vec![0; t_total];
+    let mut time_series_payoff_zealots = vec![0.0; t_total];
+    let mut time_series_intervention = vec![false; t_total];
+    let mut time_series_military_stock = vec![0.0; t_total];
+    let mut time_series_number_deterred = vec![0; t_total];
+    let mut time_series_number_truces = vec![0; t_total];
+    let mut time_series_state_pool = vec![0.0; t_total];
+    let mut time_series_state_spending = vec![0.0; t_total];
+    let mut time_series_simplex = vec![[0.0; 3]; t_total];
+    let mut time_series_technology = vec![0.0; t_total];
+    let mut time_series_technology_dispersion = vec![0.0; t_total];
+
+    let snapshot_at = |t: usize| match temporal_network {
+        Some(temporal_network) => {
+            temporal_network.snapshot_at(t, pars_model.t_snapshot, pars_model.flag_temporal_cycle)
+        }
+        None => 0,
+    };
+    let mut snapshot_current = 0;
+
+    let mut degree_sequence: Vec<usize> = agent_ensemble
+        .inner()
+        .iter()
+        .map(|agent| agent.neighbors.len())
+        .collect();
 
     let mut t = 0;
 
     while t < t_total {
-        let mut interactions = HashSet::new();
+        apply_schedules(&mut pars_scheduled, pars_model, schedules, t);
+        for intervention in interventions {
+            if intervention.t == t {
+                apply_intervention(agent_ensemble, &intervention.action, &mut degree_sequence);
+                time_series_intervention[t] = true;
+            }
+            if let InterventionAction::Disarm { duration } = intervention.action {
+                if intervention.t <= t && t < intervention.t + duration {
+                    pars_scheduled.fraction_investment = 0.0;
+                }
+            }
+        }
+        let pars_model = &pars_scheduled;
+
+        if matches!(
+            pars_model.model_network,
+            NetworkModel::Annealed | NetworkModel::AnnealedMixed
+        ) {
+            agent_ensemble.rewire_annealed(&degree_sequence, pars_model.model_network, &mut rng);
+        }
+
+        if let Some(temporal_network) = temporal_network {
+            let snapshot = snapshot_at(t);
+            if snapshot != snapshot_current {
+                agent_ensemble.set_network(
+                    &temporal_network.snapshots[snapshot],
+                    pars_model.flag_directed,
+                );
+                snapshot_current = snapshot;
+            }
+        }
+        time_series_snapshot[t] = snapshot_current;
+
+        truces.retain(|_, t_end| *t_end > t as f64);
+        time_series_number_truces[t] = truces.len();
+        (
+            time_series_technology[t],
+            time_series_technology_dispersion[t],
+        ) = technology_moments(agent_ensemble);
+
+        if pars_model.flag_mixed {
+            for agent in agent_ensemble.inner_mut().iter_mut() {
+                if !agent.flag_zealot {
+                    agent.sample_mixed(&mut rng);
+                }
+            }
+        }
 
         for focal_agent in 0..nagents {
+            if pars_model.flag_mixed {
+                for (average, probability) in time_series_simplex[t]
+                    .iter_mut()
+                    .zip(agent_ensemble.inner()[focal_agent].mixed)
+                {
+                    *average += probability / nagents as f64;
+                }
+            }
+            time_series_military_stock[t] +=
+                agent_ensemble.inner()[focal_agent].military_stock / nagents as f64;
+
+            if agent_ensemble.inner()[focal_agent].flag_zealot {
+                time_series_number_zealots[t] += 1;
+                time_series_payoff_zealots[t] +=
+                    agent_ensemble.inner()[focal_agent].resources_cumulative;
+            }
+
             match agent_ensemble.inner()[focal_agent].strategy {
                 Strategy::Cooperator => {
                     if t >= t_equilibrium {
@@ -175,157 +424,312 @@ pub fn dynamical_loop(agent_ensemble: &mut AgentEnsemble, pars_model: &Input) ->
                         agent_ensemble.inner()[focal_agent].resources_cumulative;
                 }
             };
+        }
 
-            let focal_neighbors = agent_ensemble.inner()[focal_agent].neighbors.clone();
-
-            let mut focal_nfighters = 0;
-            for focal_neighbor in &focal_neighbors {
-                if agent_ensemble.inner()[*focal_neighbor].strategy == Strategy::Fighter {
-                    focal_nfighters += 1;
-                }
-            }
+        time_series_payoff_cooperators[t] /= time_series_number_cooperators[t] as f64;
+        time_series_payoff_defectors[t] /= time_series_number_defectors[t] as f64;
+        time_series_payoff_fighters[t] /= time_series_number_fighters[t] as f64;
+        if time_series_number_zealots[t] > 0 {
+            time_series_payoff_zealots[t] /= time_series_number_zealots[t] as f64;
+        }
 
-            for focal_neighbor in focal_neighbors {
-                let interaction_pair = if focal_agent < focal_neighbor {
-                    (focal_agent, focal_neighbor)
-                } else {
-                    (focal_neighbor, focal_agent)
-                };
+        // Sampled pure strategies are not absorbing while mixed vectors keep evolving.
+        if !pars_model.flag_mixed
+            && (time_series_number_cooperators[t] == nagents
+                || time_series_number_defectors[t] == nagents)
+        {
+            println!("Absorbing state reached at t={}", t);
+            break;
+        }
 
-                if interactions.contains(&interaction_pair) {
+        let nfighters = agent_ensemble.number_of_fighters();
+
+        for round in 0..pars_model.number_rounds {
+            let mut interactions = HashSet::new();
+
+            for focal_agent in 0..nagents {
+                if pars_model.model_network == NetworkModel::WellMixed {
+                    time_series_number_deterred[t] += interact_well_mixed(
+                        agent_ensemble,
+                        focal_agent,
+                        nfighters,
+                        pars_model,
+                        t,
+                        &mut rng,
+                        &mut event_ensemble,
+                    );
                     continue;
                 }
 
-                interactions.insert(interaction_pair);
+                let focal_neighbors = agent_ensemble.inner()[focal_agent].neighbors.clone();
+                let focal_weights = agent_ensemble.inner()[focal_agent].weights.clone();
 
-                if agent_ensemble.inner()[focal_agent].strategy == Strategy::Fighter
-                    || agent_ensemble.inner()[focal_neighbor].strategy == Strategy::Fighter
-                {
-                    let focal_war_resources =
-                        if agent_ensemble.inner()[focal_agent].strategy == Strategy::Fighter {
-                            pars_model.fraction_investment
-                                * agent_ensemble.inner()[focal_agent].resources_cumulative
-                                / agent_ensemble.inner()[focal_agent].neighbors.len() as f64
+                for (focal_neighbor, weight) in focal_neighbors.into_iter().zip(focal_weights) {
+                    let interaction_pair =
+                        if pars_model.flag_directed || focal_agent < focal_neighbor {
+                            (focal_agent, focal_neighbor)
                         } else {
-                            pars_model.fraction_investment
-                                * agent_ensemble.inner()[focal_agent].resources_cumulative
-                                / focal_nfighters as f64
+                            (focal_neighbor, focal_agent)
                         };
 
-                    let enemy_war_resources = if agent_ensemble.inner()[focal_neighbor].strategy
-                        == Strategy::Fighter
-                    {
-                        pars_model.fraction_investment
-                            * agent_ensemble.inner()[focal_neighbor].resources_cumulative
-                            / agent_ensemble.inner()[focal_neighbor].neighbors.len() as f64
-                    } else {
-                        let mut enemy_nfighters = 0;
-                        let enemy_neighbors = &agent_ensemble.inner()[focal_neighbor].neighbors;
-                        for enemy_neighbor in enemy_neighbors {
-                            if agent_ensemble.inner()[*enemy_neighbor].strategy == Strategy::Fighter
-                            {
-                                enemy_nfighters += 1;
-                            }
-                        }
+                    if interactions.contains(&interaction_pair) {
+                        continue;
+                    }
 
-                        pars_model.fraction_investment
-                            * agent_ensemble.inner()[focal_neighbor].resources_cumulative
-                            / enemy_nfighters as f64
+                    interactions.insert(interaction_pair);
+
+                    let weight_payoff = if pars_model.flag_weight_payoff {
+                        weight
+                    } else {
+                        1.0
                     };
 
-                    if focal_war_resources + enemy_war_resources > pars_model.cutoff_resources {
-                        let csf_probability = tullock_csf(
-                            focal_war_resources,
-                            enemy_war_resources,
-                            pars_model.parameter_technology,
-                        );
+                    let focal_is_fighter =
+                        agent_ensemble.inner()[focal_agent].strategy == Strategy::Fighter;
+                    let enemy_is_fighter =
+                        agent_ensemble.inner()[focal_neighbor].strategy == Strategy::Fighter;
 
-                        let trial: f64 = rng.gen();
+                    if (focal_is_fighter || (enemy_is_fighter && !pars_model.flag_directed))
+                        && pars_model.model_contest == ContestModel::Multiparty
+                    {
+                        continue;
+                    }
 
-                        let winner = if trial < csf_probability {
-                            agent_ensemble.inner_mut()[focal_agent].resources_instant +=
-                                enemy_war_resources;
-                            agent_ensemble.inner_mut()[focal_neighbor].resources_instant -=
-                                enemy_war_resources;
-                            0
-                        } else {
-                            agent_ensemble.inner_mut()[focal_agent].resources_instant -=
-                                focal_war_resources;
-                            agent_ensemble.inner_mut()[focal_neighbor].resources_instant +=
-                                focal_war_resources;
-                            1
-                        };
+                    // A directed edge only lets the focal agent attack; the target defends with the
+                    // share of its resources set by the fighters among its in-neighbors.
+                    if focal_is_fighter || (enemy_is_fighter && !pars_model.flag_directed) {
+                        let edge = edge_key(focal_agent, focal_neighbor, pars_model.flag_directed);
+                        if flag_truce(&truces, edge, t as f64, pars_model, &mut rng) {
+                            continue;
+                        }
 
-                        event_count += 1;
-
-                        let event = FightingEvent {
-                            id_enemy: focal_neighbor,
-                            id_event: event_count,
-                            id_focal: focal_agent,
-                            investment_enemy: enemy_war_resources,
-                            investment_focal: focal_war_resources,
-                            resources_enemy: agent_ensemble.inner()[focal_neighbor]
-                                .resources_cumulative,
-                            resources_focal: agent_ensemble.inner()[focal_agent]
-                                .resources_cumulative,
-                            strategy_enemy: agent_ensemble.inner()[focal_neighbor].strategy,
-                            strategy_focal: agent_ensemble.inner()[focal_agent].strategy,
-                            time: t,
-                            winner,
-                        };
+                        let focal = fight_side(
+                            agent_ensemble,
+                            focal_agent,
+                            weight,
+                            focal_is_fighter,
+                            pars_model,
+                        );
+                        let enemy = fight_side(
+                            agent_ensemble,
+                            focal_neighbor,
+                            weight,
+                            enemy_is_fighter && !pars_model.flag_directed,
+                            pars_model,
+                        );
+                        let focal_war_resources = focal.war_resources;
+                        let enemy_war_resources = enemy.war_resources;
+
+                        if focal_war_resources + enemy_war_resources > pars_model.cutoff_resources {
+                            let csf_probability = tullock_csf(
+                                focal_war_resources
+                                    * agent_ensemble.inner()[focal_agent]
+                                        .effectiveness(pars_model.flag_technology),
+                                enemy_war_resources
+                                    * agent_ensemble.inner()[focal_neighbor]
+                                        .effectiveness(pars_model.flag_technology),
+                                pars_model.parameter_technology,
+                            );
+
+                            if flag_deterred(
+                                focal_is_fighter,
+                                enemy_is_fighter && !pars_model.flag_directed,
+                                csf_probability,
+                                &focal,
+                                &enemy,
+                                pars_model,
+                            ) {
+                                time_series_number_deterred[t] += 1;
+                                continue;
+                            }
 
-                        event_ensemble.push(event);
-                    }
-                } else {
-                    if agent_ensemble.inner()[focal_agent].strategy == Strategy::Cooperator {
-                        if agent_ensemble.inner()[focal_neighbor].strategy == Strategy::Cooperator {
-                            agent_ensemble.inner_mut()[focal_agent].resources_instant +=
-                                pars_model.payoff_cooperation;
-                            agent_ensemble.inner_mut()[focal_neighbor].resources_instant +=
-                                pars_model.payoff_cooperation;
-                        } else {
-                            agent_ensemble.inner_mut()[focal_neighbor].resources_instant +=
-                                pars_model.payoff_defection;
+                            if let Some((id_extorter, id_victim, amount)) = extort(
+                                agent_ensemble,
+                                focal_agent,
+                                focal_neighbor,
+                                focal_is_fighter,
+                                enemy_is_fighter && !pars_model.flag_directed,
+                                csf_probability,
+                                &focal,
+                                &enemy,
+                                pars_model,
+                                t,
+                                &mut tribute_ensemble,
+                            ) {
+                                agent_ensemble.inner_mut()[id_extorter].resources_instant += amount;
+                                agent_ensemble.inner_mut()[id_victim].resources_instant -= amount;
+                                continue;
+                            }
+
+                            let ((focal_gain, focal_loss), (enemy_gain, enemy_loss), winner) =
+                                fight_outcome(
+                                    csf_probability,
+                                    &focal,
+                                    &enemy,
+                                    pars_model,
+                                    &mut rng,
+                                );
+                            book_fight(
+                                &mut agent_ensemble.inner_mut()[focal_agent],
+                                focal_gain,
+                                focal_loss,
+                                pars_model,
+                                true,
+                            );
+                            book_fight(
+                                &mut agent_ensemble.inner_mut()[focal_neighbor],
+                                enemy_gain,
+                                enemy_loss,
+                                pars_model,
+                                true,
+                            );
+
+                            if pars_model.t_truce > 0 {
+                                truces.insert(edge, (t + 1 + pars_model.t_truce) as f64);
+                            }
+                            record_fight(
+                                agent_ensemble,
+                                focal_agent,
+                                focal_neighbor,
+                                pars_model,
+                                &mut rng,
+                            );
+
+                            let (id_winner, id_loser, probability_winner) = if winner == 0 {
+                                (focal_agent, focal_neighbor, csf_probability)
+                            } else {
+                                (focal_neighbor, focal_agent, 1.0 - csf_probability)
+                            };
+                            if agent_ensemble.inner()[id_winner].strategy == Strategy::Fighter
+                                && probability_winner >= pars_model.threshold_conquest
+                            {
+                                conquests.push((id_winner, id_loser));
+                            }
+
+                            event_count += 1;
+
+                            let event = FightingEvent {
+                                id_enemy: focal_neighbor,
+                                id_event: event_count,
+                                id_focal: focal_agent,
+                                investment_enemy: enemy_war_resources,
+                                investment_focal: focal_war_resources,
+                                military_stock_enemy: agent_ensemble.inner()[focal_neighbor]
+                                    .military_stock,
+                                military_stock_focal: agent_ensemble.inner()[focal_agent]
+                                    .military_stock,
+                                resources_enemy: agent_ensemble.inner()[focal_neighbor]
+                                    .resources_cumulative,
+                                resources_focal: agent_ensemble.inner()[focal_agent]
+                                    .resources_cumulative,
+                                strategy_enemy: agent_ensemble.inner()[focal_neighbor].strategy,
+                                strategy_focal: agent_ensemble.inner()[focal_agent].strategy,
+                                time: t,
+                                winner,
+                            };
+
+                            event_ensemble.push(event);
                         }
-                    } else if agent_ensemble.inner()[focal_neighbor].strategy
-                        == Strategy::Cooperator
-                    {
+                    } else if !enemy_is_fighter {
+                        let (focal_payoff, enemy_payoff) = play_game(
+                            agent_ensemble,
+                            focal_agent,
+                            focal_neighbor,
+                            pars_model,
+                            &mut rng,
+                        );
                         agent_ensemble.inner_mut()[focal_agent].resources_instant +=
-                            pars_model.payoff_defection;
+                            focal_payoff * weight_payoff;
+                        agent_ensemble.inner_mut()[focal_neighbor].resources_instant +=
+                            enemy_payoff * weight_payoff;
                     }
                 }
             }
-        }
 
-        time_series_payoff_cooperators[t] /= time_series_number_cooperators[t] as f64;
-        time_series_payoff_defectors[t] /= time_series_number_defectors[t] as f64;
-        time_series_payoff_fighters[t] /= time_series_number_fighters[t] as f64;
+            if pars_model.model_contest == ContestModel::Multiparty
+                && pars_model.model_network != NetworkModel::WellMixed
+            {
+                for target in 0..nagents {
+                    time_series_number_deterred[t] += interact_contest(
+                        agent_ensemble,
+                        target,
+                        pars_model,
+                        t,
+                        &mut rng,
+                        &mut contest_ensemble,
+                        &mut conquests,
+                        &mut truces,
+                    );
+                }
+            }
 
-        if time_series_number_cooperators[t] == nagents
-            || time_series_number_defectors[t] == nagents
-        {
-            println!("Absorbing state reached at t={}", t);
-            break;
-        }
+            for focal_agent in 0..nagents {
+                agent_ensemble.inner_mut()[focal_agent].resources_cumulative +=
+                    agent_ensemble.inner()[focal_agent].resources_instant;
+                agent_ensemble.inner_mut()[focal_agent]
+                    .record_payoff(pars_model.rate_discount, pars_model.t_window);
+            }
 
-        for focal_agent in 0..nagents {
-            agent_ensemble.inner_mut()[focal_agent].resources_cumulative +=
-                agent_ensemble.inner()[focal_agent].resources_instant;
+            if round + 1 < pars_model.number_rounds {
+                for focal_agent in 0..nagents {
+                    agent_ensemble.inner_mut()[focal_agent].resources_cumulative =
+                        agent_ensemble.inner()[focal_agent].resources_cumulative
+                            * (1.0 - pars_model.rate_consumption);
+                    agent_ensemble.inner_mut()[focal_agent].resources_instant = 0.0;
+                }
+            }
         }
 
         for focal_agent in 0..nagents {
-            let nneighbors = agent_ensemble.inner()[focal_agent].neighbors.len();
-            let trial = rand::thread_rng().gen_range(0..nneighbors);
-            let focal_neighbor = agent_ensemble.inner()[focal_agent].neighbors[trial];
+            if agent_ensemble.inner()[focal_agent].flag_zealot
+                || (pars_model.probability_update < 1.0
+                    && rng.gen::<f64>() >= pars_model.probability_update)
+            {
+                agent_ensemble.inner_mut()[focal_agent].strategy_temp =
+                    agent_ensemble.inner()[focal_agent].strategy;
+                continue;
+            }
 
-            let resource_delta = agent_ensemble.inner()[focal_agent].resources_cumulative
-                - agent_ensemble.inner()[focal_neighbor].resources_cumulative;
+            let focal_neighbor = match if pars_model.model_network == NetworkModel::WellMixed {
+                sample_opponent(nagents, focal_agent, &mut rng)
+            } else {
+                sample_neighbor(
+                    &agent_ensemble.inner()[focal_agent],
+                    pars_model.flag_weight_imitation,
+                    &mut rng,
+                )
+            } {
+                Some(focal_neighbor) => focal_neighbor,
+                None => {
+                    agent_ensemble.inner_mut()[focal_agent].strategy_temp =
+                        agent_ensemble.inner()[focal_agent].strategy;
+                    continue;
+                }
+            };
+
+            let resource_delta = agent_ensemble.inner()[focal_agent]
+                .fitness(pars_model.model_fitness)
+                - agent_ensemble.inner()[focal_neighbor].fitness(pars_model.model_fitness);
             let fermi_probability =
                 1.0 / (1.0 + f64::exp(resource_delta / pars_model.parameter_noise));
             let trial: f64 = rng.gen();
             if trial < fermi_probability {
                 agent_ensemble.inner_mut()[focal_agent].strategy_temp =
                     agent_ensemble.inner()[focal_neighbor].strategy;
+                agent_ensemble.inner_mut()[focal_agent].flag_payer_temp =
+                    agent_ensemble.inner()[focal_neighbor].flag_payer;
+                agent_ensemble.inner_mut()[focal_agent].technology_temp =
+                    agent_ensemble.inner()[focal_neighbor].technology;
+                if pars_model.flag_mixed {
+                    let mixed_model = agent_ensemble.inner()[focal_neighbor].mixed;
+                    agent_ensemble.inner_mut()[focal_agent].mixed_temp =
+                        agent_ensemble.inner()[focal_agent].blend_mixed(
+                            &mixed_model,
+                            pars_model.rate_blend,
+                            pars_model.parameter_mutation,
+                            &mut rng,
+                        );
+                }
             } else {
                 agent_ensemble.inner_mut()[focal_agent].strategy_temp =
                     agent_ensemble.inner()[focal_agent].strategy;
@@ -335,6 +739,12 @@ pub fn dynamical_loop(agent_ensemble: &mut AgentEnsemble, pars_model: &Input) ->
         for focal_agent in 0..nagents {
             agent_ensemble.inner_mut()[focal_agent].strategy =
                 agent_ensemble.inner()[focal_agent].strategy_temp;
+            agent_ensemble.inner_mut()[focal_agent].flag_payer =
+                agent_ensemble.inner()[focal_agent].flag_payer_temp;
+            agent_ensemble.inner_mut()[focal_agent].mixed =
+                agent_ensemble.inner()[focal_agent].mixed_temp;
+            agent_ensemble.inner_mut()[focal_agent].technology =
+                agent_ensemble.inner()[focal_agent].technology_temp;
 
             agent_ensemble.inner_mut()[focal_agent].resources_cumulative =
                 agent_ensemble.inner()[focal_agent].resources_cumulative
@@ -342,11 +752,33 @@ pub fn dynamical_loop(agent_ensemble: &mut AgentEnsemble, pars_model: &Input) ->
 
             agent_ensemble.inner_mut()[focal_agent].resources_instant = 0.0;
 
+            if pars_model.flag_military_stock {
+                agent_ensemble.inner_mut()[focal_agent].arm(
+                    pars_model.rate_armament,
+                    pars_model.rate_depreciation,
+                    1.0,
+                );
+            }
+            if pars_model.flag_technology {
+                agent_ensemble.inner_mut()[focal_agent]
+                    .research(pars_model.rate_research, pars_model.parameter_research);
+            }
+
             if agent_ensemble.inner()[focal_agent].resources_cumulative < 0.0 {
                 println!("Negative resources alert for {}", focal_agent);
             }
         }
 
+        if pars_model.model_state != StateModel::None {
+            time_series_state_spending[t] =
+                govern(agent_ensemble, &mut state_pool, pars_model, &mut rng);
+            time_series_state_pool[t] = state_pool;
+        }
+
+        for (id_winner, id_loser) in conquests.drain(..) {
+            conquer(agent_ensemble, id_winner, id_loser, pars_model);
+        }
+
         if t >= t_equilibrium && t % 250 == 0 {
             println!(
                 "t={}, avg cooperators {}, defectors {}, fighters {}",
@@ -387,6 +819,15 @@ pub fn dynamical_loop(agent_ensemble: &mut AgentEnsemble, pars_model: &Input) ->
             time_series_payoff_cooperators[remaining_t] = time_series_payoff_cooperators[last_time];
             time_series_payoff_defectors[remaining_t] = time_series_payoff_defectors[last_time];
             time_series_payoff_fighters[remaining_t] = time_series_payoff_fighters[last_time];
+            time_series_snapshot[remaining_t] = snapshot_at(remaining_t);
+            time_series_number_zealots[remaining_t] = time_series_number_zealots[last_time];
+            time_series_payoff_zealots[remaining_t] = time_series_payoff_zealots[last_time];
+            time_series_military_stock[remaining_t] = time_series_military_stock[last_time];
+            time_series_state_pool[remaining_t] = time_series_state_pool[last_time];
+            time_series_simplex[remaining_t] = time_series_simplex[last_time];
+            time_series_technology[remaining_t] = time_series_technology[last_time];
+            time_series_technology_dispersion[remaining_t] =
+                time_series_technology_dispersion[last_time];
         }
     } else {
         fraction_cooperators = avg_fraction_cooperators;
@@ -413,21 +854,975 @@ pub fn dynamical_loop(agent_ensemble: &mut AgentEnsemble, pars_model: &Input) ->
         payoff_cooperators: time_series_payoff_cooperators,
         payoff_defectors: time_series_payoff_defectors,
         payoff_fighters: time_series_payoff_fighters,
+        snapshot: time_series_snapshot,
+        number_zealots: time_series_number_zealots,
+        payoff_zealots: time_series_payoff_zealots,
+        intervention: time_series_intervention,
+        military_stock: time_series_military_stock,
+        number_deterred: time_series_number_deterred,
+        number_truces: time_series_number_truces,
+        state_pool: time_series_state_pool,
+        state_spending: time_series_state_spending,
+        simplex: time_series_simplex,
+        technology: time_series_technology,
+        technology_dispersion: time_series_technology_dispersion,
     };
 
+    let territory = agent_ensemble
+        .inner()
+        .iter()
+        .map(|agent| agent.territory)
+        .collect();
+
     Output {
         global: output_global,
+        agents: Some(snapshot_agents(agent_ensemble)),
+        contests: Some(contest_ensemble),
         events: Some(event_ensemble),
+        territory: Some(territory),
         time: Some(output_time),
+        tributes: Some(tribute_ensemble),
+    }
+}
+
+/// Applies a scripted intervention to the population. Isolated agents also lose their stubs in
+/// the annealed degree sequence, so stub matching does not reconnect them.
+pub fn apply_intervention(
+    agent_ensemble: &mut AgentEnsemble,
+    action: &InterventionAction,
+    degree_sequence: &mut [usize],
+) {
+    match action {
+        InterventionAction::Aid { strategy, amount } => {
+            agent_ensemble.inject_resources(*strategy, *amount)
+        }
+        InterventionAction::Convert {
+            ids,
+            strategy_old,
+            strategy_new,
+        } => agent_ensemble.convert_strategy(ids, *strategy_old, *strategy_new),
+        InterventionAction::Disarm { .. } => {}
+        InterventionAction::IsolateRichestFighters { number } => {
+            let mut fighters: Vec<usize> = agent_ensemble
+                .inner()
+                .iter()
+                .filter(|agent| agent.strategy == Strategy::Fighter)
+                .map(|agent| agent.id)
+                .collect();
+            fighters.sort_by(|a, b| {
+                agent_ensemble.inner()[*b]
+                    .resources_cumulative
+                    .total_cmp(&agent_ensemble.inner()[*a].resources_cumulative)
+            });
+            fighters.truncate(*number);
+
+            agent_ensemble.isolate(&fighters);
+            for id in fighters {
+                degree_sequence[id] = 0;
+            }
+        }
     }
 }
 
+/// Applies a decisive victory of a fighter. `Assimilation` converts the loser to the winner's
+/// strategy, `Edge` hands the contested edge of a directed network over to the winner, leaving it
+/// as a link from the winner. Zealots cannot be assimilated. Each conquest that changes something
+/// adds one to the winner's territory.
+pub fn conquer(
+    agent_ensemble: &mut AgentEnsemble,
+    id_winner: usize,
+    id_loser: usize,
+    pars_model: &Input,
+) {
+    let flag_conquered = match pars_model.model_conquest {
+        ConquestModel::None => false,
+        ConquestModel::Assimilation => {
+            let strategy = agent_ensemble.inner()[id_winner].strategy;
+            let loser = &agent_ensemble.inner()[id_loser];
+            if loser.flag_zealot || loser.strategy == strategy {
+                false
+            } else {
+                agent_ensemble.convert_strategy(&[id_loser], None, strategy);
+                true
+            }
+        }
+        ConquestModel::Edge => agent_ensemble.seize_edge(id_loser, id_winner),
+    };
+
+    if flag_conquered {
+        agent_ensemble.inner_mut()[id_winner].territory += 1;
+    }
+}
+
+/// Picks the committed agents: a random `fraction_zealots` of the population, the same number of
+/// highest-degree nodes, or the ids read from `path_zealots`.
+pub fn select_zealots(
+    agent_ensemble: &AgentEnsemble,
+    zealots_file: &[usize],
+    pars_model: &Input,
+) -> Vec<usize> {
+    let nagents = agent_ensemble.number_of_agents();
+    let nzealots = ((pars_model.fraction_zealots * nagents as f64).round() as usize).min(nagents);
+
+    match pars_model.model_zealots {
+        ZealotModel::None => Vec::new(),
+        ZealotModel::File => zealots_file
+            .iter()
+            .copied()
+            .filter(|id| *id < nagents)
+            .collect(),
+        ZealotModel::Hubs => {
+            let mut ids: Vec<usize> = (0..nagents).collect();
+            ids.sort_by_key(|id| std::cmp::Reverse(agent_ensemble.inner()[*id].neighbors.len()));
+            ids.truncate(nzealots);
+            ids
+        }
+        ZealotModel::Random => sample(&mut rand::thread_rng(), nagents, nzealots).into_vec(),
+    }
+}
+
+/// Number of edges a fighter splits its war resources across, or its total edge weight when
+/// fight intensity is weighted.
+pub fn degree_fight(agent: &Agent, pars_model: &Input) -> f64 {
+    if pars_model.flag_weight_fight {
+        agent.strength()
+    } else {
+        agent.neighbors.len() as f64
+    }
+}
+
+/// Plays `size_sample_well_mixed` interactions of the focal agent against opponents drawn
+/// uniformly from the whole population. Only the focal agent's resources change, rescaled by the
+/// number of potential opponents, so its expected payoff matches that on a complete graph.
+pub fn interact_well_mixed<R: Rng>(
+    agent_ensemble: &mut AgentEnsemble,
+    focal_agent: usize,
+    nfighters: usize,
+    pars_model: &Input,
+    t: usize,
+    rng: &mut R,
+    event_ensemble: &mut Vec<FightingEvent>,
+) -> usize {
+    let nagents = agent_ensemble.number_of_agents();
+    let mut ndeterred = 0;
+    let nopponents = (nagents - 1) as f64;
+    let scale = nopponents / pars_model.size_sample_well_mixed as f64;
+
+    let focal_strategy = agent_ensemble.inner()[focal_agent].strategy;
+    let focal_resources = agent_ensemble.inner()[focal_agent].resources_cumulative;
+    let focal_war_chest = agent_ensemble.inner()[focal_agent].war_chest(
+        pars_model.fraction_investment,
+        pars_model.flag_military_stock,
+    );
+
+    for _ in 0..pars_model.size_sample_well_mixed {
+        let enemy_agent = match sample_opponent(nagents, focal_agent, rng) {
+            Some(enemy_agent) => enemy_agent,
+            None => return ndeterred,
+        };
+        let enemy_strategy = agent_ensemble.inner()[enemy_agent].strategy;
+        let enemy_resources = agent_ensemble.inner()[enemy_agent].resources_cumulative;
+        let enemy_war_chest = agent_ensemble.inner()[enemy_agent].war_chest(
+            pars_model.fraction_investment,
+            pars_model.flag_military_stock,
+        );
+
+        if focal_strategy == Strategy::Fighter || enemy_strategy == Strategy::Fighter {
+            let focal_share = if focal_strategy == Strategy::Fighter {
+                1.0 / nopponents
+            } else {
+                1.0 / nfighters as f64
+            };
+            let enemy_share = if enemy_strategy == Strategy::Fighter {
+                1.0 / nopponents
+            } else {
+                1.0 / nfighters as f64
+            };
+            let focal = FightSide {
+                resources: focal_resources + agent_ensemble.inner()[focal_agent].resources_instant,
+                share: focal_share,
+                war_resources: focal_war_chest * focal_share,
+            };
+            let enemy = FightSide {
+                resources: enemy_resources,
+                share: enemy_share,
+                war_resources: enemy_war_chest * enemy_share,
+            };
+            let focal_war_resources = focal.war_resources;
+            let enemy_war_resources = enemy.war_resources;
+
+            if focal_war_resources + enemy_war_resources > pars_model.cutoff_resources {
+                let csf_probability = tullock_csf(
+                    focal_war_resources
+                        * agent_ensemble.inner()[focal_agent]
+                            .effectiveness(pars_model.flag_technology),
+                    enemy_war_resources
+                        * agent_ensemble.inner()[enemy_agent]
+                            .effectiveness(pars_model.flag_technology),
+                    pars_model.parameter_technology,
+                );
+
+                if flag_deterred(
+                    focal_strategy == Strategy::Fighter,
+                    enemy_strategy == Strategy::Fighter,
+                    csf_probability,
+                    &focal,
+                    &enemy,
+                    pars_model,
+                ) {
+                    ndeterred += 1;
+                    continue;
+                }
+
+                let ((focal_gain, focal_loss), _, winner) =
+                    fight_outcome(csf_probability, &focal, &enemy, pars_model, rng);
+                book_fight(
+                    &mut agent_ensemble.inner_mut()[focal_agent],
+                    focal_gain * scale,
+                    focal_loss * scale,
+                    pars_model,
+                    true,
+                );
+
+                let event = FightingEvent {
+                    id_enemy: enemy_agent,
+                    id_event: event_ensemble.len() + 1,
+                    id_focal: focal_agent,
+                    investment_enemy: enemy_war_resources,
+                    investment_focal: focal_war_resources,
+                    military_stock_enemy: agent_ensemble.inner()[enemy_agent].military_stock,
+                    military_stock_focal: agent_ensemble.inner()[focal_agent].military_stock,
+                    resources_enemy: enemy_resources,
+                    resources_focal: focal_resources,
+                    strategy_enemy: enemy_strategy,
+                    strategy_focal: focal_strategy,
+                    time: t,
+                    winner,
+                };
+
+                event_ensemble.push(event);
+            }
+        } else {
+            let (focal_payoff, _) =
+                play_game(agent_ensemble, focal_agent, enemy_agent, pars_model, rng);
+            agent_ensemble.inner_mut()[focal_agent].resources_instant += focal_payoff * scale;
+        }
+    }
+
+    ndeterred
+}
+
+/// Resolves one Tullock contest at `target` among the target and every fighter that can attack
+/// it. Attackers commit their per-edge war resources and the target its whole investment; one
+/// participant wins with probability `x_i^gamma / sum_j x_j^gamma` and takes the stakes of all
+/// the others, with the same stake, destruction and expected-value rules as pairwise fights.
+/// Every attack is recorded in the target's memory and the attackers' reputations.
+#[allow(clippy::too_many_arguments)]
+pub fn interact_contest<R: Rng>(
+    agent_ensemble: &mut AgentEnsemble,
+    target: usize,
+    pars_model: &Input,
+    t: usize,
+    rng: &mut R,
+    contest_ensemble: &mut Vec<ContestEvent>,
+    conquests: &mut Vec<(usize, usize)>,
+    truces: &mut HashMap<(usize, usize), f64>,
+) -> usize {
+    let mut ndeterred = 0;
+    let agent_target = &agent_ensemble.inner()[target];
+    let side_target = FightSide {
+        resources: agent_target.resources_cumulative + agent_target.resources_instant,
+        share: 1.0,
+        war_resources: agent_target.war_chest(
+            pars_model.fraction_investment,
+            pars_model.flag_military_stock,
+        ),
+    };
+    let mut participants = vec![ContestParticipant {
+        id: target,
+        investment: side_target.war_resources,
+        resources: agent_target.resources_cumulative,
+        strategy: agent_target.strategy,
+    }];
+    let mut sides = vec![side_target];
+    for (attacker, weight) in agent_target
+        .neighbors_in
+        .iter()
+        .zip(&agent_target.weights_in)
+    {
+        let agent_attacker = &agent_ensemble.inner()[*attacker];
+        if agent_attacker.strategy != Strategy::Fighter
+            || flag_truce(
+                truces,
+                edge_key(*attacker, target, pars_model.flag_directed),
+                t as f64,
+                pars_model,
+                rng,
+            )
+        {
+            continue;
+        }
+
+        let side_attacker = fight_side(agent_ensemble, *attacker, *weight, true, pars_model);
+        let investment = side_attacker.war_resources;
+
+        if flag_deterred(
+            true,
+            false,
+            tullock_csf(
+                investment * agent_attacker.effectiveness(pars_model.flag_technology),
+                participants[0].investment * agent_target.effectiveness(pars_model.flag_technology),
+                pars_model.parameter_technology,
+            ),
+            &side_attacker,
+            &side_target,
+            pars_model,
+        ) {
+            ndeterred += 1;
+            continue;
+        }
+
+        participants.push(ContestParticipant {
+            id: *attacker,
+            investment,
+            resources: agent_attacker.resources_cumulative,
+            strategy: agent_attacker.strategy,
+        });
+        sides.push(side_attacker);
+    }
+
+    let investments: Vec<f64> = participants.iter().map(|p| p.investment).collect();
+    if participants.len() < 2 || investments.iter().sum::<f64>() <= pars_model.cutoff_resources {
+        return ndeterred;
+    }
+
+    let stakes: Vec<f64> = sides
+        .iter()
+        .map(|side| fight_stake(side, pars_model))
+        .collect();
+    let stake_total: f64 = stakes.iter().sum();
+    let retained = 1.0 - pars_model.fraction_destruction;
+    let efforts: Vec<f64> = participants
+        .iter()
+        .map(|participant| {
+            participant.investment
+                * agent_ensemble.inner()[participant.id].effectiveness(pars_model.flag_technology)
+        })
+        .collect();
+    let csf_probabilities = tullock_csf_multi(&efforts, pars_model.parameter_technology);
+
+    let winner = if pars_model.model_fight == FightModel::ExpectedValue {
+        for (i, participant) in participants.iter().enumerate() {
+            book_fight(
+                &mut agent_ensemble.inner_mut()[participant.id],
+                csf_probabilities[i] * retained * (stake_total - stakes[i]),
+                (1.0 - csf_probabilities[i]) * stakes[i],
+                pars_model,
+                true,
+            );
+        }
+        (0..participants.len())
+            .max_by(|a, b| csf_probabilities[*a].total_cmp(&csf_probabilities[*b]))
+            .unwrap()
+    } else {
+        let winner = match WeightedIndex::new(&csf_probabilities) {
+            Ok(dist) => dist.sample(rng),
+            Err(_) => return ndeterred,
+        };
+        for (i, participant) in participants.iter().enumerate() {
+            let (gain, loss) = if i == winner {
+                (retained * (stake_total - stakes[i]), 0.0)
+            } else {
+                (0.0, stakes[i])
+            };
+            book_fight(
+                &mut agent_ensemble.inner_mut()[participant.id],
+                gain,
+                loss,
+                pars_model,
+                true,
+            );
+        }
+        winner
+    };
+
+    let target_standing = standing(agent_ensemble, target);
+    for participant in participants.iter().skip(1) {
+        if pars_model.model_reciprocity != ReciprocityModel::None {
+            agent_ensemble.remember(target, participant.id, Action::Attack);
+        }
+        observe(
+            agent_ensemble,
+            participant.id,
+            target_standing,
+            Action::Attack,
+            pars_model,
+            rng,
+        );
+    }
+
+    if winner != 0
+        && participants[winner].strategy == Strategy::Fighter
+        && csf_probabilities[winner] >= pars_model.threshold_conquest
+    {
+        conquests.push((participants[winner].id, target));
+    }
+
+    if pars_model.t_truce > 0 {
+        for participant in participants.iter().skip(1) {
+            truces.insert(
+                edge_key(participant.id, target, pars_model.flag_directed),
+                (t + 1 + pars_model.t_truce) as f64,
+            );
+        }
+    }
+
+    contest_ensemble.push(ContestEvent {
+        id_event: contest_ensemble.len() + 1,
+        id_target: target,
+        id_winner: participants[winner].id,
+        participants,
+        time: t,
+    });
+
+    ndeterred
+}
+
+/// Plays one round of the dilemma between two non-fighters and returns their payoffs. Under a
+/// reciprocity model each side also remembers the other's action, and under a reputation model
+/// each action is assessed with probability `probability_observation`.
+pub fn play_game<R: Rng>(
+    agent_ensemble: &mut AgentEnsemble,
+    focal_agent: usize,
+    enemy_agent: usize,
+    pars_model: &Input,
+    rng: &mut R,
+) -> (f64, f64) {
+    let focal_action = agent_ensemble.action(
+        focal_agent,
+        enemy_agent,
+        pars_model.model_reciprocity,
+        pars_model.model_reputation,
+    );
+    let enemy_action = agent_ensemble.action(
+        enemy_agent,
+        focal_agent,
+        pars_model.model_reciprocity,
+        pars_model.model_reputation,
+    );
+
+    if pars_model.model_reciprocity != ReciprocityModel::None {
+        agent_ensemble.remember(focal_agent, enemy_agent, enemy_action);
+        agent_ensemble.remember(enemy_agent, focal_agent, focal_action);
+    }
+    let focal_standing = standing(agent_ensemble, focal_agent);
+    let enemy_standing = standing(agent_ensemble, enemy_agent);
+    observe(
+        agent_ensemble,
+        focal_agent,
+        enemy_standing,
+        focal_action,
+        pars_model,
+        rng,
+    );
+    observe(
+        agent_ensemble,
+        enemy_agent,
+        focal_standing,
+        enemy_action,
+        pars_model,
+        rng,
+    );
+
+    match (focal_action, enemy_action) {
+        (Action::Cooperate, Action::Cooperate) => {
+            (pars_model.payoff_cooperation, pars_model.payoff_cooperation)
+        }
+        (Action::Cooperate, Action::Defect) => (0.0, pars_model.payoff_defection),
+        (Action::Defect, Action::Cooperate) => (pars_model.payoff_defection, 0.0),
+        _ => (0.0, 0.0),
+    }
+}
+
+/// Records the attacks on a fought edge in the memories and reputations of the agents.
+pub fn record_fight<R: Rng>(
+    agent_ensemble: &mut AgentEnsemble,
+    focal_agent: usize,
+    enemy_agent: usize,
+    pars_model: &Input,
+    rng: &mut R,
+) {
+    let focal_standing = standing(agent_ensemble, focal_agent);
+    let enemy_standing = standing(agent_ensemble, enemy_agent);
+    for (attacker, target, target_standing) in [
+        (focal_agent, enemy_agent, enemy_standing),
+        (enemy_agent, focal_agent, focal_standing),
+    ] {
+        if agent_ensemble.inner()[attacker].strategy != Strategy::Fighter {
+            continue;
+        }
+
+        if pars_model.model_reciprocity != ReciprocityModel::None {
+            agent_ensemble.remember(target, attacker, Action::Attack);
+        }
+        observe(
+            agent_ensemble,
+            attacker,
+            target_standing,
+            Action::Attack,
+            pars_model,
+            rng,
+        );
+    }
+}
+
+/// Strategy and standing of an agent as seen by observers, taken before any assessment of the
+/// current interaction.
+fn standing(agent_ensemble: &AgentEnsemble, id: usize) -> (Strategy, bool) {
+    let agent = &agent_ensemble.inner()[id];
+    (agent.strategy, agent.flag_good())
+}
+
+/// Lets the public assess `donor` for playing `action` towards a recipient with the given
+/// standing, with probability `probability_observation`.
+fn observe<R: Rng>(
+    agent_ensemble: &mut AgentEnsemble,
+    donor: usize,
+    (strategy_recipient, flag_good_recipient): (Strategy, bool),
+    action: Action,
+    pars_model: &Input,
+    rng: &mut R,
+) {
+    if pars_model.model_reputation == ReputationModel::None
+        || rng.gen::<f64>() >= pars_model.probability_observation
+    {
+        return;
+    }
+
+    agent_ensemble.inner_mut()[donor].assess(
+        action,
+        strategy_recipient,
+        flag_good_recipient,
+        pars_model.model_reputation,
+    );
+}
+
+/// Collects a `rate_tax` share of every agent's resources into the state pool and spends it
+/// according to `model_state`. The police splits the pool evenly among fighters and wins a
+/// Tullock contest against each fighter's war chest with the probability set by the CSF; seized war
+/// chests return to the pool. Returns the amount spent.
+pub fn govern<R: Rng>(
+    agent_ensemble: &mut AgentEnsemble,
+    state_pool: &mut f64,
+    pars_model: &Input,
+    rng: &mut R,
+) -> f64 {
+    for agent in agent_ensemble.inner_mut().iter_mut() {
+        let tax = pars_model.rate_tax * agent.resources_cumulative.max(0.0);
+        agent.resources_cumulative -= tax;
+        *state_pool += tax;
+    }
+
+    let recipients: Vec<usize> = agent_ensemble
+        .inner()
+        .iter()
+        .filter(|agent| match pars_model.model_state {
+            StateModel::None => false,
+            StateModel::Equal => true,
+            StateModel::Police => agent.strategy == Strategy::Fighter,
+            StateModel::Subsidy => agent.strategy == Strategy::Cooperator,
+        })
+        .map(|agent| agent.id)
+        .collect();
+    if recipients.is_empty() {
+        return 0.0;
+    }
+
+    let spending = *state_pool;
+    let share = spending / recipients.len() as f64;
+    *state_pool = 0.0;
+
+    for id in recipients {
+        let agent = &mut agent_ensemble.inner_mut()[id];
+        if pars_model.model_state != StateModel::Police {
+            agent.resources_cumulative += share;
+            continue;
+        }
+
+        let war_resources = agent.war_chest(
+            pars_model.fraction_investment,
+            pars_model.flag_military_stock,
+        );
+        if share + war_resources <= pars_model.cutoff_resources {
+            continue;
+        }
+        if rng.gen::<f64>()
+            < tullock_csf(
+                share,
+                war_resources * agent.effectiveness(pars_model.flag_technology),
+                pars_model.parameter_technology,
+            )
+        {
+            if pars_model.flag_military_stock {
+                agent.military_stock -= war_resources;
+            } else {
+                agent.resources_cumulative -= war_resources;
+            }
+            *state_pool += war_resources;
+        }
+    }
+
+    spending
+}
+
+pub fn snapshot_agents(agent_ensemble: &AgentEnsemble) -> Vec<AgentSnapshot> {
+    agent_ensemble
+        .inner()
+        .iter()
+        .map(|agent| AgentSnapshot {
+            degree: agent.neighbors.len(),
+            flag_payer: agent.flag_payer,
+            id: agent.id,
+            image_score: agent.image_score,
+            military_stock: agent.military_stock,
+            resources_cumulative: agent.resources_cumulative,
+            strategy: agent.strategy,
+            technology: agent.technology,
+            territory: agent.territory,
+        })
+        .collect()
+}
+
+/// Lets a lone attacker demand a `fraction_tribute` share of a non-fighter's resources before
+/// fighting. Depending on `model_tribute` the victim always pays, pays when the tribute is below
+/// its expected loss from the fight, or follows its heritable trait. The tribute is capped at what
+/// the victim has left. Every demand is logged; returns the extorter, the victim and the amount
+/// when the tribute is paid.
+#[allow(clippy::too_many_arguments)]
+pub fn extort(
+    agent_ensemble: &AgentEnsemble,
+    focal_agent: usize,
+    enemy_agent: usize,
+    focal_attacks: bool,
+    enemy_attacks: bool,
+    csf_probability: f64,
+    focal: &FightSide,
+    enemy: &FightSide,
+    pars_model: &Input,
+    t: usize,
+    tribute_ensemble: &mut Vec<TributeEvent>,
+) -> Option<(usize, usize, f64)> {
+    if pars_model.model_tribute == TributeModel::None || focal_attacks == enemy_attacks {
+        return None;
+    }
+
+    let (id_extorter, id_victim, csf_extorter, side_extorter, side_victim) = if focal_attacks {
+        (focal_agent, enemy_agent, csf_probability, focal, enemy)
+    } else {
+        (
+            enemy_agent,
+            focal_agent,
+            1.0 - csf_probability,
+            enemy,
+            focal,
+        )
+    };
+    let victim = &agent_ensemble.inner()[id_victim];
+    if victim.strategy == Strategy::Fighter {
+        return None;
+    }
+
+    let amount = (pars_model.fraction_tribute * victim.resources_cumulative.max(0.0))
+        .min((victim.resources_cumulative + victim.resources_instant).max(0.0));
+    let flag_paid = match pars_model.model_tribute {
+        TributeModel::None => false,
+        TributeModel::Always => true,
+        TributeModel::Rational => {
+            amount
+                <= -expected_gain_attack(1.0 - csf_extorter, side_victim, side_extorter, pars_model)
+        }
+        TributeModel::Trait => victim.flag_payer,
+    };
+
+    tribute_ensemble.push(TributeEvent {
+        amount,
+        flag_paid,
+        id_event: tribute_ensemble.len() + 1,
+        id_extorter,
+        id_victim,
+        time: t,
+    });
+
+    flag_paid.then_some((id_extorter, id_victim, amount))
+}
+
+/// Mean and standard deviation of technology across the population.
+pub fn technology_moments(agent_ensemble: &AgentEnsemble) -> (f64, f64) {
+    let nagents = agent_ensemble.number_of_agents() as f64;
+    let mean = agent_ensemble
+        .inner()
+        .iter()
+        .map(|agent| agent.technology)
+        .sum::<f64>()
+        / nagents;
+    let variance = agent_ensemble
+        .inner()
+        .iter()
+        .map(|agent| (agent.technology - mean).powi(2))
+        .sum::<f64>()
+        / nagents;
+
+    (mean, variance.sqrt())
+}
+
+/// Key of the edge from `id_a` to `id_b`; on undirected networks both directions share a key.
+pub fn edge_key(id_a: usize, id_b: usize, flag_directed: bool) -> (usize, usize) {
+    if flag_directed {
+        (id_a, id_b)
+    } else {
+        (id_a.min(id_b), id_a.max(id_b))
+    }
+}
+
+/// Whether a fight on `edge` is prevented by a truce lasting beyond `time`. Truces are broken
+/// with probability `probability_truce_breach`.
+pub fn flag_truce<R: Rng>(
+    truces: &HashMap<(usize, usize), f64>,
+    edge: (usize, usize),
+    time: f64,
+    pars_model: &Input,
+    rng: &mut R,
+) -> bool {
+    match truces.get(&edge) {
+        Some(t_end) if time < *t_end => rng.gen::<f64>() >= pars_model.probability_truce_breach,
+        _ => false,
+    }
+}
+
+/// Draws an agent other than the focal one uniformly from the population.
+pub fn sample_opponent<R: Rng>(nagents: usize, focal_agent: usize, rng: &mut R) -> Option<usize> {
+    if nagents < 2 {
+        return None;
+    }
+
+    let opponent = rng.gen_range(0..nagents - 1);
+    Some(if opponent >= focal_agent {
+        opponent + 1
+    } else {
+        opponent
+    })
+}
+
+/// Draws an imitation partner uniformly or proportionally to edge weight. Returns `None` for
+/// isolated agents.
+pub fn sample_neighbor<R: Rng>(
+    agent: &Agent,
+    flag_weight_imitation: bool,
+    rng: &mut R,
+) -> Option<usize> {
+    if agent.neighbors.is_empty() {
+        return None;
+    }
+
+    if flag_weight_imitation {
+        let dist = WeightedIndex::new(&agent.weights).ok()?;
+        Some(agent.neighbors[dist.sample(rng)])
+    } else {
+        Some(agent.neighbors[rng.gen_range(0..agent.neighbors.len())])
+    }
+}
+
+/// Gains and losses of the attacker and the defender after a fight, and the winner (0 for the
+/// attacker). `Investment` transfers the loser's war resources, `Plunder` a `fraction_plunder`
+/// share of the resources the loser commits to this fight. Losses are capped at what is left.
+/// `ExpectedValue` settles the fight deterministically at the expected transfers of
+/// `Investment`, and the winner is the side favored by the CSF. In every model a
+/// `fraction_destruction` share of the transfer is lost on the way to the winner.
+pub fn fight_outcome<R: Rng>(
+    csf_probability: f64,
+    focal: &FightSide,
+    enemy: &FightSide,
+    pars_model: &Input,
+    rng: &mut R,
+) -> ((f64, f64), (f64, f64), usize) {
+    let retained = 1.0 - pars_model.fraction_destruction;
+    let focal_stake = fight_stake(focal, pars_model);
+    let enemy_stake = fight_stake(enemy, pars_model);
+
+    match pars_model.model_fight {
+        FightModel::ExpectedValue => {
+            let focal_outcome = (
+                csf_probability * retained * enemy_stake,
+                (1.0 - csf_probability) * focal_stake,
+            );
+            let enemy_outcome = (
+                (1.0 - csf_probability) * retained * focal_stake,
+                csf_probability * enemy_stake,
+            );
+            let winner = if csf_probability >= 0.5 { 0 } else { 1 };
+            (focal_outcome, enemy_outcome, winner)
+        }
+        FightModel::Investment | FightModel::Plunder => {
+            let trial: f64 = rng.gen();
+            if trial < csf_probability {
+                ((retained * enemy_stake, 0.0), (0.0, enemy_stake), 0)
+            } else {
+                ((0.0, focal_stake), (retained * focal_stake, 0.0), 1)
+            }
+        }
+    }
+}
+
+/// Whether fight losses come out of the military stock rather than out of resources, which is
+/// the case when war resources are at stake and a military stock is modeled.
+fn flag_loss_from_stock(pars_model: &Input) -> bool {
+    pars_model.flag_military_stock && pars_model.model_fight != FightModel::Plunder
+}
+
+/// Books a fight's gain as resources, into `resources_instant` when `flag_instant` is set and
+/// straight into `resources_cumulative` otherwise. The loss comes out of the military stock or
+/// out of resources, and never exceeds what is left there.
+pub fn book_fight(agent: &mut Agent, gain: f64, loss: f64, pars_model: &Input, flag_instant: bool) {
+    if flag_loss_from_stock(pars_model) {
+        agent.military_stock -= loss.min(agent.military_stock.max(0.0));
+    } else {
+        let available = if flag_instant {
+            agent.resources_cumulative + agent.resources_instant
+        } else {
+            agent.resources_cumulative
+        };
+        let loss = loss.min(available.max(0.0));
+        if flag_instant {
+            agent.resources_instant -= loss;
+        } else {
+            agent.resources_cumulative -= loss;
+        }
+    }
+
+    if flag_instant {
+        agent.resources_instant += gain;
+    } else {
+        agent.resources_cumulative += gain;
+    }
+}
+
+/// One side of a pairwise fight.
+#[derive(Clone, Copy)]
+pub struct FightSide {
+    /// Resources the agent has left to lose in the current round.
+    pub resources: f64,
+    /// Share of the agent's resources committed to this fight.
+    pub share: f64,
+    pub war_resources: f64,
+}
+
+/// Side of `id` in a fight on an edge of weight `weight`. Attackers split their resources over
+/// their edges, defenders over the fighters among their in-neighbors.
+pub fn fight_side(
+    agent_ensemble: &AgentEnsemble,
+    id: usize,
+    weight: f64,
+    flag_attacker: bool,
+    pars_model: &Input,
+) -> FightSide {
+    let agent = &agent_ensemble.inner()[id];
+    let weight_fight = if pars_model.flag_weight_fight {
+        weight
+    } else {
+        1.0
+    };
+
+    let nfights = if flag_attacker {
+        degree_fight(agent, pars_model)
+    } else {
+        let mut nfighters = 0.0;
+        for (neighbor, weight_in) in agent.neighbors_in.iter().zip(&agent.weights_in) {
+            if agent_ensemble.inner()[*neighbor].strategy == Strategy::Fighter {
+                nfighters += if pars_model.flag_weight_fight {
+                    *weight_in
+                } else {
+                    1.0
+                };
+            }
+        }
+        nfighters
+    };
+    let share = weight_fight / nfights;
+
+    FightSide {
+        resources: agent.resources_cumulative + agent.resources_instant,
+        share,
+        war_resources: agent.war_chest(
+            pars_model.fraction_investment,
+            pars_model.flag_military_stock,
+        ) * share,
+    }
+}
+
+/// Resources an agent puts at risk in a fight: its war resources, or a `fraction_plunder` part
+/// of the share of its resources committed to the fight. Stakes paid out of resources are
+/// capped at what the agent has left; war resources drawn from the military stock never exceed it.
+pub fn fight_stake(side: &FightSide, pars_model: &Input) -> f64 {
+    match pars_model.model_fight {
+        FightModel::Plunder => (pars_model.fraction_plunder * side.share * side.resources)
+            .clamp(0.0, side.resources.max(0.0)),
+        _ if flag_loss_from_stock(pars_model) => side.war_resources.max(0.0),
+        _ => side.war_resources.clamp(0.0, side.resources.max(0.0)),
+    }
+}
+
+/// Expected resource change of an attacker that wins with `csf_probability`, given the stakes of
+/// the current fight model.
+pub fn expected_gain_attack(
+    csf_probability: f64,
+    focal: &FightSide,
+    enemy: &FightSide,
+    pars_model: &Input,
+) -> f64 {
+    csf_probability * (1.0 - pars_model.fraction_destruction) * fight_stake(enemy, pars_model)
+        - (1.0 - csf_probability) * fight_stake(focal, pars_model)
+}
+
+/// Whether a fight is called off because no fighter on the edge expects a gain above
+/// `threshold_deterrence`.
+pub fn flag_deterred(
+    focal_attacks: bool,
+    enemy_attacks: bool,
+    csf_probability: f64,
+    focal: &FightSide,
+    enemy: &FightSide,
+    pars_model: &Input,
+) -> bool {
+    if !pars_model.flag_deterrence {
+        return false;
+    }
+
+    let focal_deterred = !focal_attacks
+        || expected_gain_attack(csf_probability, focal, enemy, pars_model)
+            <= pars_model.threshold_deterrence;
+    let enemy_deterred = !enemy_attacks
+        || expected_gain_attack(1.0 - csf_probability, enemy, focal, pars_model)
+            <= pars_model.threshold_deterrence;
+
+    focal_deterred && enemy_deterred
+}
+
 pub fn tullock_csf(resource_focal: f64, resource_enemy: f64, parameter_technology: f64) -> f64 {
     let x = f64::powf(resource_focal, parameter_technology);
     let y = f64::powf(resource_enemy, parameter_technology);
     x / (x + y)
 }
 
+/// Winning probabilities `x_i^gamma / sum_j x_j^gamma` of an N-player Tullock contest.
+pub fn tullock_csf_multi(resources: &[f64], parameter_technology: f64) -> Vec<f64> {
+    let powers: Vec<f64> = resources
+        .iter()
+        .map(|resource| f64::powf(*resource, parameter_technology))
+        .collect();
+    let total: f64 = powers.iter().sum();
+    powers.iter().map(|power| power / total).collect()
+}
+
 pub fn update_rule_best(agent_ensemble: &mut AgentEnsemble, focal_agent: usize) {
     let focal_payoff = agent_ensemble.inner()[focal_agent].resources_cumulative;
     let mut best_payoff = focal_payoff;
@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use crate::{
+    agent::{AgentEnsemble, StateModel, Strategy},
+    core::{
+        book_fight, edge_key, extort, fight_outcome, fight_side, flag_deterred, flag_truce, govern,
+        play_game, record_fight, sample_neighbor, snapshot_agents, technology_moments, tullock_csf,
+    },
+    utils::{FightingEvent, Input, Output, OutputGlobal, TimeSeries, TributeEvent},
+};
+
+/// Continuous-time counterpart of `dynamical_loop`. Imitations happen at rate `rate_imitation`
+/// per agent, and every edge carries fights at rate `rate_fight` when a fighter can attack along
+/// it or games at rate `rate_game` when neither end fights. Events are drawn with the Gillespie
+/// direct method; edge events are proposed at the larger edge rate and thinned. Resources decay
+/// continuously as `(1 - rate_consumption)^dt` and are updated lazily, together with arming. The
+/// state is sampled on the integer time grid. Temporal and annealed networks and mixed strategies
+/// are not supported in this engine.
+pub fn gillespie_loop(agent_ensemble: &mut AgentEnsemble, pars_model: &Input) -> Output {
+    let mut rng = rand::thread_rng();
+
+    let nagents = agent_ensemble.number_of_agents();
+
+    let t_equilibrium = pars_model.t_equilibrium;
+    let t_average = pars_model.t_average;
+    let t_total = t_equilibrium + t_average;
+
+    let mut edges: Vec<(usize, usize, f64)> = Vec::new();
+    for agent in agent_ensemble.inner() {
+        for (neighbor, weight) in agent.neighbors.iter().zip(&agent.weights) {
+            if pars_model.flag_directed || agent.id < *neighbor {
+                edges.push((agent.id, *neighbor, *weight));
+            }
+        }
+    }
+
+    let rate_edge = pars_model.rate_fight.max(pars_model.rate_game);
+    let rate_total_imitation = pars_model.rate_imitation * nagents as f64;
+    let rate_total_edge = rate_edge * edges.len() as f64;
+    let rate_total = rate_total_imitation + rate_total_edge;
+
+    let decay = 1.0 - pars_model.rate_consumption;
+    let mut time_last_update = vec![0.0; nagents];
+    let mut refresh = |agent_ensemble: &mut AgentEnsemble, id: usize, time: f64| {
+        let dt = time - time_last_update[id];
+        let agent = &mut agent_ensemble.inner_mut()[id];
+        agent.resources_cumulative *= decay.powf(dt);
+        if pars_model.flag_military_stock {
+            agent.arm(pars_model.rate_armament, pars_model.rate_depreciation, dt);
+        }
+        time_last_update[id] = time;
+    };
+
+    let mut avg_fraction_cooperators: f64 = 0.0;
+    let mut avg_fraction_defectors: f64 = 0.0;
+    let mut avg_fraction_fighters: f64 = 0.0;
+    let mut avg_payoff_cooperators: f64 = 0.0;
+    let mut avg_payoff_defectors: f64 = 0.0;
+    let mut avg_payoff_fighters: f64 = 0.0;
+
+    let mut event_ensemble: Vec<FightingEvent> = Vec::new();
+    let mut tribute_ensemble: Vec<TributeEvent> = Vec::new();
+
+    let mut time_series_number_cooperators = vec![0; t_total];
+    let mut time_series_number_defectors = vec![0; t_total];
+    let mut time_series_number_fighters = vec![0; t_total];
+    let mut time_series_payoff_cooperators = vec![0.0; t_total];
+    let mut time_series_payoff_defectors = vec![0.0; t_total];
+    let mut time_series_payoff_fighters = vec![0.0; t_total];
+    let mut time_series_number_zealots = vec![0; t_total];
+    let mut time_series_payoff_zealots = vec![0.0; t_total];
+    let mut time_series_military_stock = vec![0.0; t_total];
+    let mut time_series_number_deterred = vec![0; t_total];
+    let mut time_series_number_truces = vec![0; t_total];
+    let mut time_series_state_pool = vec![0.0; t_total];
+    let mut time_series_state_spending = vec![0.0; t_total];
+    let mut time_series_technology = vec![0.0; t_total];
+    let mut time_series_technology_dispersion = vec![0.0; t_total];
+    let mut truces: HashMap<(usize, usize), f64> = HashMap::new();
+    let mut state_pool = 0.0;
+
+    let mut time = 0.0;
+    let mut t = 0;
+    let mut flag_absorbing = false;
+
+    while t < t_total {
+        let time_next = if rate_total > 0.0 {
+            time - f64::ln(1.0 - rng.gen::<f64>()) / rate_total
+        } else {
+            f64::INFINITY
+        };
+
+        while t < t_total && (t as f64) < time_next {
+            truces.retain(|_, t_end| *t_end > t as f64);
+            time_series_number_truces[t] = truces.len();
+            (
+                time_series_technology[t],
+                time_series_technology_dispersion[t],
+            ) = technology_moments(agent_ensemble);
+
+            for focal_agent in 0..nagents {
+                refresh(agent_ensemble, focal_agent, t as f64);
+
+                let resources = agent_ensemble.inner()[focal_agent].resources_cumulative;
+                if agent_ensemble.inner()[focal_agent].flag_zealot {
+                    time_series_number_zealots[t] += 1;
+                    time_series_payoff_zealots[t] += resources;
+                }
+                time_series_military_stock[t] +=
+                    agent_ensemble.inner()[focal_agent].military_stock / nagents as f64;
+                if pars_model.flag_technology {
+                    agent_ensemble.inner_mut()[focal_agent]
+                        .research(pars_model.rate_research, pars_model.parameter_research);
+                }
+                match agent_ensemble.inner()[focal_agent].strategy {
+                    Strategy::Cooperator => {
+                        time_series_number_cooperators[t] += 1;
+                        time_series_payoff_cooperators[t] += resources;
+                    }
+                    Strategy::Defector => {
+                        time_series_number_defectors[t] += 1;
+                        time_series_payoff_defectors[t] += resources;
+                    }
+                    Strategy::Fighter => {
+                        time_series_number_fighters[t] += 1;
+                        time_series_payoff_fighters[t] += resources;
+                    }
+                }
+            }
+
+            if pars_model.model_state != StateModel::None {
+                time_series_state_spending[t] =
+                    govern(agent_ensemble, &mut state_pool, pars_model, &mut rng);
+                time_series_state_pool[t] = state_pool;
+            }
+
+            if t >= t_equilibrium {
+                avg_fraction_cooperators +=
+                    time_series_number_cooperators[t] as f64 / (nagents * t_average) as f64;
+                avg_fraction_defectors +=
+                    time_series_number_defectors[t] as f64 / (nagents * t_average) as f64;
+                avg_fraction_fighters +=
+                    time_series_number_fighters[t] as f64 / (nagents * t_average) as f64;
+                avg_payoff_cooperators += time_series_payoff_cooperators[t] / t_average as f64;
+                avg_payoff_defectors += time_series_payoff_defectors[t] / t_average as f64;
+                avg_payoff_fighters += time_series_payoff_fighters[t] / t_average as f64;
+            }
+
+            time_series_payoff_cooperators[t] /= time_series_number_cooperators[t] as f64;
+            time_series_payoff_defectors[t] /= time_series_number_defectors[t] as f64;
+            time_series_payoff_fighters[t] /= time_series_number_fighters[t] as f64;
+            if time_series_number_zealots[t] > 0 {
+                time_series_payoff_zealots[t] /= time_series_number_zealots[t] as f64;
+            }
+
+            if time_series_number_cooperators[t] == nagents
+                || time_series_number_defectors[t] == nagents
+            {
+                println!("Absorbing state reached at t={}", t);
+                flag_absorbing = true;
+                break;
+            }
+
+            if t >= t_equilibrium && t % 250 == 0 {
+                println!(
+                    "t={}, avg cooperators {}, defectors {}, fighters {}",
+                    t + 1,
+                    avg_fraction_cooperators,
+                    avg_fraction_defectors,
+                    avg_fraction_fighters,
+                );
+            }
+
+            t += 1;
+        }
+
+        if flag_absorbing || t >= t_total {
+            break;
+        }
+
+        time = time_next;
+
+        if rng.gen::<f64>() * rate_total < rate_total_imitation {
+            let focal_agent = rng.gen_range(0..nagents);
+            if agent_ensemble.inner()[focal_agent].flag_zealot {
+                continue;
+            }
+            let focal_neighbor = match sample_neighbor(
+                &agent_ensemble.inner()[focal_agent],
+                pars_model.flag_weight_imitation,
+                &mut rng,
+            ) {
+                Some(focal_neighbor) => focal_neighbor,
+                None => continue,
+            };
+
+            refresh(agent_ensemble, focal_agent, time);
+            refresh(agent_ensemble, focal_neighbor, time);
+
+            let resource_delta = agent_ensemble.inner()[focal_agent]
+                .fitness(pars_model.model_fitness)
+                - agent_ensemble.inner()[focal_neighbor].fitness(pars_model.model_fitness);
+            let fermi_probability =
+                1.0 / (1.0 + f64::exp(resource_delta / pars_model.parameter_noise));
+            let trial: f64 = rng.gen();
+            if trial < fermi_probability {
+                let strategy = agent_ensemble.inner()[focal_neighbor].strategy;
+                agent_ensemble.inner_mut()[focal_agent].strategy = strategy;
+                agent_ensemble.inner_mut()[focal_agent].strategy_temp = strategy;
+                agent_ensemble.inner_mut()[focal_agent].flag_payer =
+                    agent_ensemble.inner()[focal_neighbor].flag_payer;
+                agent_ensemble.inner_mut()[focal_agent].technology =
+                    agent_ensemble.inner()[focal_neighbor].technology;
+            }
+            continue;
+        }
+
+        let (focal_agent, focal_neighbor, weight) = edges[rng.gen_range(0..edges.len())];
+        let focal_is_fighter = agent_ensemble.inner()[focal_agent].strategy == Strategy::Fighter;
+        let enemy_is_fighter = agent_ensemble.inner()[focal_neighbor].strategy == Strategy::Fighter;
+        let flag_fight = focal_is_fighter || (enemy_is_fighter && !pars_model.flag_directed);
+
+        let rate_accepted = if flag_fight {
+            pars_model.rate_fight
+        } else if !enemy_is_fighter {
+            pars_model.rate_game
+        } else {
+            0.0
+        };
+        if rng.gen::<f64>() * rate_edge >= rate_accepted {
+            continue;
+        }
+
+        refresh(agent_ensemble, focal_agent, time);
+        refresh(agent_ensemble, focal_neighbor, time);
+
+        if flag_fight {
+            let edge = edge_key(focal_agent, focal_neighbor, pars_model.flag_directed);
+            if flag_truce(&truces, edge, time, pars_model, &mut rng) {
+                continue;
+            }
+
+            let focal = fight_side(
+                agent_ensemble,
+                focal_agent,
+                weight,
+                focal_is_fighter,
+                pars_model,
+            );
+            let enemy = fight_side(
+                agent_ensemble,
+                focal_neighbor,
+                weight,
+                enemy_is_fighter && !pars_model.flag_directed,
+                pars_model,
+            );
+            let focal_war_resources = focal.war_resources;
+            let enemy_war_resources = enemy.war_resources;
+
+            if focal_war_resources + enemy_war_resources > pars_model.cutoff_resources {
+                let csf_probability = tullock_csf(
+                    focal_war_resources
+                        * agent_ensemble.inner()[focal_agent]
+                            .effectiveness(pars_model.flag_technology),
+                    enemy_war_resources
+                        * agent_ensemble.inner()[focal_neighbor]
+                            .effectiveness(pars_model.flag_technology),
+                    pars_model.parameter_technology,
+                );
+
+                let resources_focal = agent_ensemble.inner()[focal_agent].resources_cumulative;
+                let resources_enemy = agent_ensemble.inner()[focal_neighbor].resources_cumulative;
+
+                if flag_deterred(
+                    focal_is_fighter,
+                    enemy_is_fighter && !pars_model.flag_directed,
+                    csf_probability,
+                    &focal,
+                    &enemy,
+                    pars_model,
+                ) {
+                    time_series_number_deterred[time as usize] += 1;
+                    continue;
+                }
+
+                if let Some((id_extorter, id_victim, amount)) = extort(
+                    agent_ensemble,
+                    focal_agent,
+                    focal_neighbor,
+                    focal_is_fighter,
+                    enemy_is_fighter && !pars_model.flag_directed,
+                    csf_probability,
+                    &focal,
+                    &enemy,
+                    pars_model,
+                    time as usize,
+                    &mut tribute_ensemble,
+                ) {
+                    agent_ensemble.inner_mut()[id_extorter].resources_cumulative += amount;
+                    agent_ensemble.inner_mut()[id_victim].resources_cumulative -= amount;
+                    continue;
+                }
+
+                let ((focal_gain, focal_loss), (enemy_gain, enemy_loss), winner) =
+                    fight_outcome(csf_probability, &focal, &enemy, pars_model, &mut rng);
+                book_fight(
+                    &mut agent_ensemble.inner_mut()[focal_agent],
+                    focal_gain,
+                    focal_loss,
+                    pars_model,
+                    false,
+                );
+                book_fight(
+                    &mut agent_ensemble.inner_mut()[focal_neighbor],
+                    enemy_gain,
+                    enemy_loss,
+                    pars_model,
+                    false,
+                );
+
+                if pars_model.t_truce > 0 {
+                    truces.insert(edge, time + pars_model.t_truce as f64);
+                }
+                record_fight(
+                    agent_ensemble,
+                    focal_agent,
+                    focal_neighbor,
+                    pars_model,
+                    &mut rng,
+                );
+
+                let event = FightingEvent {
+                    id_enemy: focal_neighbor,
+                    id_event: event_ensemble.len() + 1,
+                    id_focal: focal_agent,
+                    investment_enemy: enemy_war_resources,
+                    investment_focal: focal_war_resources,
+                    military_stock_enemy: agent_ensemble.inner()[focal_neighbor].military_stock,
+                    military_stock_focal: agent_ensemble.inner()[focal_agent].military_stock,
+                    resources_enemy,
+                    resources_focal,
+                    strategy_enemy: agent_ensemble.inner()[focal_neighbor].strategy,
+                    strategy_focal: agent_ensemble.inner()[focal_agent].strategy,
+                    time: time as usize,
+                    winner,
+                };
+
+                event_ensemble.push(event);
+            }
+        } else {
+            let weight_payoff = if pars_model.flag_weight_payoff {
+                weight
+            } else {
+                1.0
+            };
+
+            let (focal_payoff, enemy_payoff) = play_game(
+                agent_ensemble,
+                focal_agent,
+                focal_neighbor,
+                pars_model,
+                &mut rng,
+            );
+            agent_ensemble.inner_mut()[focal_agent].resources_cumulative +=
+                focal_payoff * weight_payoff;
+            agent_ensemble.inner_mut()[focal_neighbor].resources_cumulative +=
+                enemy_payoff * weight_payoff;
+        }
+    }
+
+    let fraction_cooperators;
+    let fraction_defectors;
+    let fraction_fighters;
+    let payoff_cooperators;
+    let payoff_defectors;
+    let payoff_fighters;
+
+    if flag_absorbing {
+        let last_time = t;
+
+        fraction_cooperators = time_series_number_cooperators[last_time] as f64 / nagents as f64;
+        fraction_defectors = time_series_number_defectors[last_time] as f64 / nagents as f64;
+        fraction_fighters = time_series_number_fighters[last_time] as f64 / nagents as f64;
+        payoff_cooperators = time_series_payoff_cooperators[last_time];
+        payoff_defectors = time_series_payoff_defectors[last_time];
+        payoff_fighters = time_series_payoff_fighters[last_time];
+
+        for remaining_t in t..t_total {
+            time_series_number_cooperators[remaining_t] = time_series_number_cooperators[last_time];
+            time_series_number_defectors[remaining_t] = time_series_number_defectors[last_time];
+            time_series_number_fighters[remaining_t] = time_series_number_fighters[last_time];
+            time_series_payoff_cooperators[remaining_t] = time_series_payoff_cooperators[last_time];
+            time_series_payoff_defectors[remaining_t] = time_series_payoff_defectors[last_time];
+            time_series_payoff_fighters[remaining_t] = time_series_payoff_fighters[last_time];
+            time_series_number_zealots[remaining_t] = time_series_number_zealots[last_time];
+            time_series_payoff_zealots[remaining_t] = time_series_payoff_zealots[last_time];
+            time_series_military_stock[remaining_t] = time_series_military_stock[last_time];
+            time_series_state_pool[remaining_t] = time_series_state_pool[last_time];
+            time_series_technology[remaining_t] = time_series_technology[last_time];
+            time_series_technology_dispersion[remaining_t] =
+                time_series_technology_dispersion[last_time];
+        }
+    } else {
+        fraction_cooperators = avg_fraction_cooperators;
+        fraction_defectors = avg_fraction_defectors;
+        fraction_fighters = avg_fraction_fighters;
+        payoff_cooperators = avg_payoff_cooperators;
+        payoff_defectors = avg_payoff_defectors;
+        payoff_fighters = avg_payoff_fighters;
+    }
+
+    let output_global = OutputGlobal {
+        fraction_cooperators,
+        fraction_defectors,
+        fraction_fighters,
+        payoff_cooperators,
+        payoff_defectors,
+        payoff_fighters,
+    };
+
+    let output_time = TimeSeries {
+        number_cooperators: time_series_number_cooperators,
+        number_defectors: time_series_number_defectors,
+        number_fighters: time_series_number_fighters,
+        payoff_cooperators: time_series_payoff_cooperators,
+        payoff_defectors: time_series_payoff_defectors,
+        payoff_fighters: time_series_payoff_fighters,
+        number_zealots: time_series_number_zealots,
+        payoff_zealots: time_series_payoff_zealots,
+        military_stock: time_series_military_stock,
+        number_deterred: time_series_number_deterred,
+        number_truces: time_series_number_truces,
+        state_pool: time_series_state_pool,
+        state_spending: time_series_state_spending,
+        technology: time_series_technology,
+        technology_dispersion: time_series_technology_dispersion,
+        ..TimeSeries::new(t_total)
+    };
+
+    Output {
+        global: output_global,
+        agents: Some(snapshot_agents(agent_ensemble)),
+        contests: None,
+        events: Some(event_ensemble),
+        territory: None,
+        tributes: Some(tribute_ensemble),
+        time: Some(output_time),
+    }
+}
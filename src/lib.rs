@@ -1,3 +1,6 @@
 pub mod agent;
 pub mod core;
+pub mod gillespie;
+pub mod meanfield;
+pub mod pairapprox;
 pub mod utils;
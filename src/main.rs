@@ -2,7 +2,15 @@ use std::env;
 
 use clap::Parser;
 use coopfight::{
-    agent::ResourceDistributionModel, core::model_cooperation_and_fight, utils::Input,
+    agent::{
+        ConquestModel, ContestModel, EngineModel, FightModel, FitnessModel, NetworkModel,
+        ReciprocityModel, ReputationModel, ResourceDistributionModel, StateModel, Strategy,
+        TributeModel, ZealotModel,
+    },
+    core::model_cooperation_and_fight,
+    meanfield::model_mean_field,
+    pairapprox::model_pair_approximation,
+    utils::{validate_input, Input},
 };
 
 #[derive(Parser, Debug)]
@@ -10,6 +18,10 @@ use coopfight::{
 pub struct Args {
     #[clap(long, value_parser, default_value_t = 0.001)]
     pub cutoff_resources: f64,
+    #[clap(long, value_parser, default_value_t = 4.0)]
+    pub degree_average: f64,
+    #[clap(long, value_parser, default_value = "json")]
+    pub extension_network: String,
     #[clap(long, value_parser, default_value_t = false)]
     pub flag_analysis_agent: bool,
     #[clap(long, value_parser, default_value_t = false)]
@@ -22,40 +34,144 @@ pub struct Args {
     pub flag_analysis_time: bool,
     #[clap(long, value_parser, default_value_t = false)]
     pub flag_config: bool,
+    #[clap(long, value_parser, default_value_t = false)]
+    pub flag_deterrence: bool,
+    #[clap(long, value_parser, default_value_t = false)]
+    pub flag_directed: bool,
+    #[clap(long, value_parser, default_value_t = false)]
+    pub flag_military_stock: bool,
+    #[clap(long, value_parser, default_value_t = false)]
+    pub flag_mixed: bool,
+    #[clap(long, value_parser, default_value_t = false)]
+    pub flag_technology: bool,
+    #[clap(long, value_parser, default_value_t = false)]
+    pub flag_temporal: bool,
+    #[clap(long, value_parser, default_value_t = false)]
+    pub flag_temporal_cycle: bool,
+    #[clap(long, value_parser, default_value_t = false)]
+    pub flag_weight_fight: bool,
+    #[clap(long, value_parser, default_value_t = false)]
+    pub flag_weight_imitation: bool,
+    #[clap(long, value_parser, default_value_t = false)]
+    pub flag_weight_payoff: bool,
     #[clap(long, value_parser, default_value_t = 0.333)]
     pub fraction_cooperators: f64,
     #[clap(long, value_parser, default_value_t = 0.333)]
     pub fraction_defectors: f64,
+    #[clap(long, value_parser, default_value_t = 0.0)]
+    pub fraction_destruction: f64,
     #[clap(long, value_parser, default_value_t = 0.1)]
     pub fraction_investment: f64,
+    #[clap(long, value_parser, default_value_t = 0.5)]
+    pub fraction_payers: f64,
+    #[clap(long, value_parser, default_value_t = 0.1)]
+    pub fraction_plunder: f64,
+    #[clap(long, value_parser, default_value_t = 0.1)]
+    pub fraction_tribute: f64,
+    #[clap(long, value_parser, default_value_t = 0.0)]
+    pub fraction_zealots: f64,
     //#[clap(long, value_parser, default_value_t = 1)]
     //pub id_experiment: usize,
     //#[clap(long, value_parser, default_value = "")]
     //pub model_game: GameModel,
     //#[clap(long, value_parser, default_value = "")]
-    //pub model_fight: FightModel,
-    //#[clap(long, value_parser, default_value = "")]
     //pub model_imitation: ImitationModel,
+    #[clap(long, value_parser, default_value = "none")]
+    pub model_conquest: ConquestModel,
+    #[clap(long, value_parser, default_value = "pairwise")]
+    pub model_contest: ContestModel,
     #[clap(long, value_parser, default_value = "uniform")]
     pub model_distribution_resources: ResourceDistributionModel,
+    #[clap(long, value_parser, default_value = "agent")]
+    pub model_engine: EngineModel,
+    #[clap(long, value_parser, default_value = "investment")]
+    pub model_fight: FightModel,
+    #[clap(long, value_parser, default_value = "cumulative")]
+    pub model_fitness: FitnessModel,
+    #[clap(long, value_parser, default_value = "quenched")]
+    pub model_network: NetworkModel,
+    #[clap(long, value_parser, default_value = "none")]
+    pub model_reciprocity: ReciprocityModel,
+    #[clap(long, value_parser, default_value = "none")]
+    pub model_reputation: ReputationModel,
+    #[clap(long, value_parser, default_value = "none")]
+    pub model_state: StateModel,
+    #[clap(long, value_parser, default_value = "none")]
+    pub model_tribute: TributeModel,
+    #[clap(long, value_parser, default_value = "none")]
+    pub model_zealots: ZealotModel,
     #[clap(long, value_parser, default_value_t = 1)]
     pub nsims: usize,
+    #[clap(long, value_parser, default_value_t = 1)]
+    pub number_rounds: usize,
+    #[clap(long, value_parser, default_value_t = 0.01)]
+    pub parameter_mutation: f64,
     #[clap(long, value_parser, default_value_t = 0.1)]
     pub parameter_noise: f64,
+    #[clap(long, value_parser, default_value_t = 0.1)]
+    pub parameter_research: f64,
     #[clap(long, value_parser, default_value_t = 0.4)]
     pub parameter_technology: f64,
     #[clap(long, value_parser, default_value_t = 1.0)]
     pub payoff_cooperation: f64,
     #[clap(long, value_parser, default_value_t = 1.1)]
     pub payoff_defection: f64,
+    #[clap(long, value_parser, default_value = "")]
+    pub path_interventions: String,
+    #[clap(long, value_parser, default_value = "")]
+    pub path_schedules: String,
+    #[clap(long, value_parser, default_value = "")]
+    pub path_zealots: String,
+    #[clap(long, value_parser, default_value_t = 1.0)]
+    pub probability_observation: f64,
+    #[clap(long, value_parser, default_value_t = 0.0)]
+    pub probability_truce_breach: f64,
+    #[clap(long, value_parser, default_value_t = 1.0)]
+    pub probability_update: f64,
+    #[clap(long, value_parser, default_value_t = 0.05)]
+    pub rate_armament: f64,
+    #[clap(long, value_parser, default_value_t = 1.0)]
+    pub rate_blend: f64,
     #[clap(long, value_parser, default_value_t = 0.1)]
     pub rate_consumption: f64,
+    #[clap(long, value_parser, default_value_t = 0.1)]
+    pub rate_depreciation: f64,
+    #[clap(long, value_parser, default_value_t = 0.1)]
+    pub rate_discount: f64,
+    #[clap(long, value_parser, default_value_t = 1.0)]
+    pub rate_fight: f64,
+    #[clap(long, value_parser, default_value_t = 1.0)]
+    pub rate_game: f64,
+    #[clap(long, value_parser, default_value_t = 1.0)]
+    pub rate_imitation: f64,
+    #[clap(long, value_parser, default_value_t = 0.0)]
+    pub rate_mutation: f64,
+    #[clap(long, value_parser, default_value_t = 0.01)]
+    pub rate_research: f64,
+    #[clap(long, value_parser, default_value_t = 0.0)]
+    pub rate_tax: f64,
+    #[clap(long, value_parser, default_value_t = 1000)]
+    pub size_population: usize,
+    #[clap(long, value_parser, default_value_t = 10)]
+    pub size_sample_well_mixed: usize,
+    #[clap(long, value_parser, default_value = "cooperator")]
+    pub strategy_zealots: Strategy,
+    #[clap(long, value_parser, default_value_t = 0.75)]
+    pub threshold_conquest: f64,
+    #[clap(long, value_parser, default_value_t = 0.0)]
+    pub threshold_deterrence: f64,
     #[clap(long, value_parser, default_value = "net_adl_lpb_nx100_ny100")]
     pub string_network: String,
     #[clap(long, value_parser, default_value_t = 1000)]
     pub t_average: usize,
     #[clap(long, value_parser, default_value_t = 10000)]
     pub t_equilibrium: usize,
+    #[clap(long, value_parser, default_value_t = 1)]
+    pub t_snapshot: usize,
+    #[clap(long, value_parser, default_value_t = 0)]
+    pub t_truce: usize,
+    #[clap(long, value_parser, default_value_t = 10)]
+    pub t_window: usize,
 }
 
 fn main() {
@@ -63,22 +179,78 @@ fn main() {
 
     let model_pars = Input {
         cutoff_resources: args.cutoff_resources,
+        degree_average: args.degree_average,
+        flag_analysis_agent: args.flag_analysis_agent,
         flag_analysis_event: args.flag_analysis_event,
         flag_analysis_global: args.flag_analysis_global,
         flag_analysis_time: args.flag_analysis_time,
+        flag_deterrence: args.flag_deterrence,
+        flag_directed: args.flag_directed,
+        flag_military_stock: args.flag_military_stock,
+        flag_mixed: args.flag_mixed,
+        flag_technology: args.flag_technology,
+        flag_temporal: args.flag_temporal,
+        flag_temporal_cycle: args.flag_temporal_cycle,
+        flag_weight_fight: args.flag_weight_fight,
+        flag_weight_imitation: args.flag_weight_imitation,
+        flag_weight_payoff: args.flag_weight_payoff,
         fraction_cooperators: args.fraction_cooperators,
         fraction_defectors: args.fraction_defectors,
+        fraction_destruction: args.fraction_destruction,
         fraction_investment: args.fraction_investment,
+        fraction_payers: args.fraction_payers,
+        fraction_plunder: args.fraction_plunder,
+        fraction_tribute: args.fraction_tribute,
+        fraction_zealots: args.fraction_zealots,
+        model_conquest: args.model_conquest,
+        model_contest: args.model_contest,
         model_distribution_resources: args.model_distribution_resources,
+        model_engine: args.model_engine,
+        model_fight: args.model_fight,
+        model_fitness: args.model_fitness,
+        model_network: args.model_network,
+        model_reciprocity: args.model_reciprocity,
+        model_reputation: args.model_reputation,
+        model_state: args.model_state,
+        model_tribute: args.model_tribute,
+        model_zealots: args.model_zealots,
         nsims: args.nsims,
+        number_rounds: args.number_rounds,
         parameter_technology: args.parameter_technology,
+        parameter_mutation: args.parameter_mutation,
         parameter_noise: args.parameter_noise,
+        parameter_research: args.parameter_research,
         payoff_cooperation: args.payoff_cooperation,
         payoff_defection: args.payoff_defection,
+        path_interventions: args.path_interventions,
+        path_schedules: args.path_schedules,
+        path_zealots: args.path_zealots,
+        probability_observation: args.probability_observation,
+        probability_truce_breach: args.probability_truce_breach,
+        probability_update: args.probability_update,
+        rate_armament: args.rate_armament,
+        rate_blend: args.rate_blend,
         rate_consumption: args.rate_consumption,
+        rate_depreciation: args.rate_depreciation,
+        rate_discount: args.rate_discount,
+        rate_fight: args.rate_fight,
+        rate_game: args.rate_game,
+        rate_imitation: args.rate_imitation,
+        rate_mutation: args.rate_mutation,
+        rate_research: args.rate_research,
+        rate_tax: args.rate_tax,
+        size_population: args.size_population,
+        size_sample_well_mixed: args.size_sample_well_mixed,
+        strategy_zealots: args.strategy_zealots,
+        threshold_conquest: args.threshold_conquest,
+        threshold_deterrence: args.threshold_deterrence,
         t_average: args.t_average,
         t_equilibrium: args.t_equilibrium,
+        t_snapshot: args.t_snapshot,
+        t_truce: args.t_truce,
+        t_window: args.t_window,
     };
+    validate_input(&model_pars).expect("Invalid input");
 
     let current_dir = env::current_dir().expect("Failed to get current directory");
     let path = current_dir
@@ -87,7 +259,16 @@ fn main() {
         .join("netrust")
         .join("data")
         .join("networks");
-    let path_network = path.join(format!("{}.json", args.string_network));
+    let path_network = path.join(format!(
+        "{}.{}",
+        args.string_network, args.extension_network
+    ));
 
-    model_cooperation_and_fight(&model_pars, &path_network);
+    match model_pars.model_engine {
+        EngineModel::Agent | EngineModel::Gillespie => {
+            model_cooperation_and_fight(&model_pars, &path_network)
+        }
+        EngineModel::MeanField => model_mean_field(&model_pars),
+        EngineModel::PairApproximation => model_pair_approximation(&model_pars),
+    }
 }
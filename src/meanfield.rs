@@ -0,0 +1,303 @@
+use std::env;
+
+use rgsl::{ODEiv2Driver, ODEiv2StepType, ODEiv2System, Value};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    core::tullock_csf,
+    utils::{construct_string_game, save_to_json, Input, TimeSeries},
+};
+
+const RESOLUTION_FIXED_POINTS: usize = 10;
+const TOLERANCE_FIXED_POINTS: f64 = 1e-4;
+const TOLERANCE_STATIONARY: f64 = 1e-8;
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct FixedPoint {
+    pub basin: f64,
+    pub fraction_cooperators: f64,
+    pub fraction_defectors: f64,
+    pub fraction_fighters: f64,
+    pub resources: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OutputMeanField {
+    pub fixed_points: Vec<FixedPoint>,
+    pub fraction_cooperators: Vec<f64>,
+    pub fraction_defectors: Vec<f64>,
+    pub fraction_fighters: Vec<f64>,
+    pub resources: Vec<f64>,
+    pub time: TimeSeries,
+}
+
+pub fn model_mean_field(pars_model: &Input) {
+    println!(
+        "COOPFIGHT mean-field. k={}, rho={}, b={}, gamma={}",
+        pars_model.degree_average,
+        pars_model.fraction_investment,
+        pars_model.payoff_defection,
+        pars_model.parameter_technology
+    );
+
+    let t_total = pars_model.t_equilibrium + pars_model.t_average;
+    let rhs = |y: &[f64], dydt: &mut [f64]| mean_field_rhs(y, dydt, pars_model);
+    let summarize = |y: &[f64]| ([y[0], y[1], y[2], y[3]], mean_field_payoffs(y, pars_model));
+
+    let state_initial = initial_state_mean_field(
+        pars_model.fraction_cooperators,
+        pars_model.fraction_defectors,
+    );
+    let trajectory = integrate_ode(&state_initial, &rhs, t_total);
+    let fixed_points = find_fixed_points(t_total, &initial_state_mean_field, &rhs, &|y| {
+        summarize(y).0
+    });
+
+    let summary: Vec<([f64; 4], [f64; 3])> = trajectory.iter().map(|y| summarize(y)).collect();
+    let output = assemble_deterministic(&summary, fixed_points, pars_model);
+    save_deterministic(&output, "coopfight_meanfield", pars_model);
+}
+
+pub fn initial_state_mean_field(fraction_cooperators: f64, fraction_defectors: f64) -> Vec<f64> {
+    vec![
+        fraction_cooperators,
+        fraction_defectors,
+        1.0 - fraction_cooperators - fraction_defectors,
+        1.0,
+    ]
+}
+
+/// Expected gain of an attacker investing `focal_war_resources` against a defender investing
+/// `enemy_war_resources`; the defender's expected gain is the opposite.
+pub fn expected_gain_fight(
+    focal_war_resources: f64,
+    enemy_war_resources: f64,
+    pars_model: &Input,
+) -> f64 {
+    if focal_war_resources + enemy_war_resources > pars_model.cutoff_resources {
+        let csf_probability = tullock_csf(
+            focal_war_resources,
+            enemy_war_resources,
+            pars_model.parameter_technology,
+        );
+        csf_probability * enemy_war_resources - (1.0 - csf_probability) * focal_war_resources
+    } else {
+        0.0
+    }
+}
+
+/// Fermi probability that an agent with `payoff_focal` adopts the strategy of one with
+/// `payoff_model`.
+pub fn fermi(payoff_focal: f64, payoff_model: f64, parameter_noise: f64) -> f64 {
+    1.0 / (1.0 + f64::exp((payoff_focal - payoff_model) / parameter_noise))
+}
+
+/// Expected one-step payoffs of cooperators, defectors and fighters when every agent holds the
+/// mean resource level and has `degree_average` neighbors drawn from the population. A defender
+/// facing a fighter splits its war resources among that fighter and the expected number of
+/// further fighters in its neighborhood.
+pub fn mean_field_payoffs(state: &[f64], pars_model: &Input) -> [f64; 3] {
+    let (fraction_cooperators, fraction_fighters, resources) = (state[0], state[2], state[3]);
+    let degree = pars_model.degree_average;
+
+    let focal_war_resources = pars_model.fraction_investment * resources / degree;
+    let enemy_war_resources =
+        pars_model.fraction_investment * resources / (1.0 + (degree - 1.0) * fraction_fighters);
+
+    let gain_fight = expected_gain_fight(focal_war_resources, enemy_war_resources, pars_model);
+
+    let payoff_cooperators = degree * fraction_cooperators * pars_model.payoff_cooperation
+        - degree * fraction_fighters * gain_fight;
+    let payoff_defectors = degree * fraction_cooperators * pars_model.payoff_defection
+        - degree * fraction_fighters * gain_fight;
+    let payoff_fighters = degree * (1.0 - fraction_fighters) * gain_fight;
+
+    [payoff_cooperators, payoff_defectors, payoff_fighters]
+}
+
+/// Right-hand side of the pairwise-comparison replicator-mutator equations with the Fermi rule,
+/// plus the relaxation of the mean resource level under consumption.
+pub fn mean_field_rhs(state: &[f64], dydt: &mut [f64], pars_model: &Input) {
+    let payoffs = mean_field_payoffs(state, pars_model);
+    let noise = pars_model.parameter_noise;
+
+    let mut payoff_mean = 0.0;
+    for s in 0..3 {
+        let mut flow = 0.0;
+        for r in 0..3 {
+            flow += state[s]
+                * state[r]
+                * (fermi(payoffs[r], payoffs[s], noise) - fermi(payoffs[s], payoffs[r], noise));
+        }
+        dydt[s] = flow + pars_model.rate_mutation * (1.0 / 3.0 - state[s]);
+        payoff_mean += state[s] * payoffs[s];
+    }
+
+    dydt[3] =
+        (1.0 - pars_model.rate_consumption) * payoff_mean - pars_model.rate_consumption * state[3];
+}
+
+/// Integrates `dy/dt = rhs(y)` and samples the state at every integer time step.
+pub fn integrate_ode(
+    state_initial: &[f64],
+    rhs: &dyn Fn(&[f64], &mut [f64]),
+    t_total: usize,
+) -> Vec<Vec<f64>> {
+    let mut function = |_t: f64, y: &[f64], dydt: &mut [f64]| {
+        rhs(y, dydt);
+        Value::Success
+    };
+    let mut system = ODEiv2System::new(state_initial.len(), &mut function);
+    let mut driver =
+        ODEiv2Driver::alloc_y_new(&mut system, &ODEiv2StepType::rkf45(), 1e-3, 1e-10, 1e-8)
+            .expect("Failed to allocate ODE driver");
+
+    let mut state = state_initial.to_vec();
+    let mut trajectory = vec![state.clone()];
+    let mut t = 0.0;
+
+    for step in 1..t_total {
+        if driver.apply(&mut t, step as f64, &mut state) != Value::Success {
+            println!("ODE integration failed at t={}", t);
+            break;
+        }
+        trajectory.push(state.clone());
+    }
+
+    trajectory
+}
+
+/// Integrates from a grid of initial compositions on the simplex and clusters the stationary end
+/// states, reduced to strategy fractions and resources. Each attractor is reported with the
+/// fraction of initial conditions that reached it.
+pub fn find_fixed_points(
+    t_total: usize,
+    initial_state: &dyn Fn(f64, f64) -> Vec<f64>,
+    rhs: &dyn Fn(&[f64], &mut [f64]),
+    reduce: &dyn Fn(&[f64]) -> [f64; 4],
+) -> Vec<FixedPoint> {
+    let mut fixed_points: Vec<FixedPoint> = Vec::new();
+    let mut nconditions = 0;
+
+    for i in 0..=RESOLUTION_FIXED_POINTS {
+        for j in 0..=(RESOLUTION_FIXED_POINTS - i) {
+            let state_initial = initial_state(
+                i as f64 / RESOLUTION_FIXED_POINTS as f64,
+                j as f64 / RESOLUTION_FIXED_POINTS as f64,
+            );
+            nconditions += 1;
+
+            let trajectory = integrate_ode(&state_initial, rhs, t_total);
+            let state_final = trajectory.last().unwrap();
+
+            let mut dydt = vec![0.0; state_final.len()];
+            rhs(state_final, &mut dydt);
+            if dydt.iter().any(|rate| rate.abs() > TOLERANCE_STATIONARY) {
+                continue;
+            }
+
+            let state = reduce(state_final);
+            match fixed_points.iter_mut().find(|fixed_point| {
+                (fixed_point.fraction_cooperators - state[0]).abs() < TOLERANCE_FIXED_POINTS
+                    && (fixed_point.fraction_defectors - state[1]).abs() < TOLERANCE_FIXED_POINTS
+                    && (fixed_point.fraction_fighters - state[2]).abs() < TOLERANCE_FIXED_POINTS
+            }) {
+                Some(fixed_point) => fixed_point.basin += 1.0,
+                None => fixed_points.push(FixedPoint {
+                    basin: 1.0,
+                    fraction_cooperators: state[0],
+                    fraction_defectors: state[1],
+                    fraction_fighters: state[2],
+                    resources: state[3],
+                }),
+            }
+        }
+    }
+
+    for fixed_point in fixed_points.iter_mut() {
+        fixed_point.basin /= nconditions as f64;
+
+        println!(
+            "Fixed point: cooperators={}, defectors={}, fighters={}, resources={}, basin={}",
+            fixed_point.fraction_cooperators,
+            fixed_point.fraction_defectors,
+            fixed_point.fraction_fighters,
+            fixed_point.resources,
+            fixed_point.basin
+        );
+    }
+
+    fixed_points
+}
+
+/// Packs a deterministic trajectory of strategy fractions, mean resources and expected payoffs
+/// into the simulation output layout. Counts are fractions scaled to `size_population`, and
+/// strategy resources are the mean level plus the expected payoff.
+pub fn assemble_deterministic(
+    trajectory: &[([f64; 4], [f64; 3])],
+    fixed_points: Vec<FixedPoint>,
+    pars_model: &Input,
+) -> OutputMeanField {
+    let t_total = pars_model.t_equilibrium + pars_model.t_average;
+    let state_last = *trajectory.last().unwrap();
+    let size = pars_model.size_population as f64;
+
+    let mut output = OutputMeanField {
+        fixed_points,
+        fraction_cooperators: Vec::with_capacity(t_total),
+        fraction_defectors: Vec::with_capacity(t_total),
+        fraction_fighters: Vec::with_capacity(t_total),
+        resources: Vec::with_capacity(t_total),
+        time: TimeSeries {
+            number_cooperators: Vec::with_capacity(t_total),
+            number_defectors: Vec::with_capacity(t_total),
+            number_fighters: Vec::with_capacity(t_total),
+            payoff_cooperators: Vec::with_capacity(t_total),
+            payoff_defectors: Vec::with_capacity(t_total),
+            payoff_fighters: Vec::with_capacity(t_total),
+            ..TimeSeries::new(t_total)
+        },
+    };
+
+    for t in 0..t_total {
+        let (state, payoffs) = trajectory.get(t).copied().unwrap_or(state_last);
+
+        output.fraction_cooperators.push(state[0]);
+        output.fraction_defectors.push(state[1]);
+        output.fraction_fighters.push(state[2]);
+        output.resources.push(state[3]);
+        output
+            .time
+            .number_cooperators
+            .push((state[0] * size).round() as usize);
+        output
+            .time
+            .number_defectors
+            .push((state[1] * size).round() as usize);
+        output
+            .time
+            .number_fighters
+            .push((state[2] * size).round() as usize);
+        output.time.payoff_cooperators.push(state[3] + payoffs[0]);
+        output.time.payoff_defectors.push(state[3] + payoffs[1]);
+        output.time.payoff_fighters.push(state[3] + payoffs[2]);
+    }
+
+    output
+}
+
+pub fn save_deterministic(output: &OutputMeanField, header: &str, pars_model: &Input) {
+    let path = env::current_dir()
+        .expect("Failed to get current directory")
+        .join("results")
+        .join("curated");
+    let string_game = format!(
+        "{}_{}_k{}.json",
+        header,
+        construct_string_game(pars_model),
+        pars_model.degree_average
+    );
+    let _ = save_to_json(output, &path.join(string_game));
+
+    println!("The game is over!");
+}
@@ -0,0 +1,332 @@
+use crate::{
+    meanfield::{
+        assemble_deterministic, expected_gain_fight, fermi, find_fixed_points, integrate_ode,
+        save_deterministic,
+    },
+    utils::Input,
+};
+
+const INDEX_COOPERATOR: usize = 0;
+const INDEX_DEFECTOR: usize = 1;
+const INDEX_FIGHTER: usize = 2;
+const INDEX_RESOURCES: usize = 9;
+const NSTRATEGIES: usize = 3;
+
+/// Neighborhood compositions `(n_C, n_D, n_F)` of a given size with their multinomial
+/// coefficients.
+type Compositions = Vec<([usize; NSTRATEGIES], f64)>;
+
+pub fn model_pair_approximation(pars_model: &Input) {
+    let degree = pars_model.degree_average.round() as usize;
+
+    println!(
+        "COOPFIGHT pair approximation. k={}, rho={}, b={}, gamma={}",
+        degree,
+        pars_model.fraction_investment,
+        pars_model.payoff_defection,
+        pars_model.parameter_technology
+    );
+
+    let t_total = pars_model.t_equilibrium + pars_model.t_average;
+    let compositions_full = compositions(degree);
+    let compositions_rest = compositions(degree - 1);
+
+    let rhs = |y: &[f64], dydt: &mut [f64]| {
+        pair_approximation_rhs(y, dydt, &compositions_full, &compositions_rest, pars_model)
+    };
+    let reduce = |y: &[f64]| {
+        let singlets = singlets(y);
+        [singlets[0], singlets[1], singlets[2], y[INDEX_RESOURCES]]
+    };
+
+    let state_initial = initial_state_pair_approximation(
+        pars_model.fraction_cooperators,
+        pars_model.fraction_defectors,
+    );
+    let trajectory = integrate_ode(&state_initial, &rhs, t_total);
+    let fixed_points = find_fixed_points(t_total, &initial_state_pair_approximation, &rhs, &reduce);
+
+    let summary: Vec<([f64; 4], [f64; 3])> = trajectory
+        .iter()
+        .map(|y| {
+            (
+                reduce(y),
+                pair_approximation_payoffs(y, &compositions_full, pars_model),
+            )
+        })
+        .collect();
+    let output = assemble_deterministic(&summary, fixed_points, pars_model);
+    save_deterministic(&output, "coopfight_pairapprox", pars_model);
+}
+
+/// Uncorrelated initial pairs `p_ab = x_a x_b` with unit resources.
+pub fn initial_state_pair_approximation(
+    fraction_cooperators: f64,
+    fraction_defectors: f64,
+) -> Vec<f64> {
+    let fractions = [
+        fraction_cooperators,
+        fraction_defectors,
+        1.0 - fraction_cooperators - fraction_defectors,
+    ];
+
+    let mut state = vec![0.0; INDEX_RESOURCES + 1];
+    for a in 0..NSTRATEGIES {
+        for b in 0..NSTRATEGIES {
+            state[a * NSTRATEGIES + b] = fractions[a] * fractions[b];
+        }
+    }
+    state[INDEX_RESOURCES] = 1.0;
+
+    state
+}
+
+/// Right-hand side of the pair approximation on a k-regular graph. The state holds the ordered
+/// pair probabilities `p_ab` followed by the mean resource level. A focal agent imitates a random
+/// neighbor with the Fermi rule; the neighborhoods of both are drawn from the conditional pair
+/// probabilities, and every pair of the focal agent changes when it switches strategy.
+pub fn pair_approximation_rhs(
+    state: &[f64],
+    dydt: &mut [f64],
+    compositions_full: &Compositions,
+    compositions_rest: &Compositions,
+    pars_model: &Input,
+) {
+    let degree = pars_model.degree_average.round();
+    let singlets = singlets(state);
+    let conditionals = conditionals(state, &singlets);
+    let fighters_defender = fighters_defender(&conditionals, degree);
+    let resources = state[INDEX_RESOURCES];
+
+    dydt.iter_mut().for_each(|rate| *rate = 0.0);
+
+    let mut apply_switch = |from: usize, to: usize, counts: &[usize; NSTRATEGIES], rate: f64| {
+        for a in 0..NSTRATEGIES {
+            for b in 0..NSTRATEGIES {
+                let delta_a = (a == to) as i32 - (a == from) as i32;
+                let delta_b = (b == to) as i32 - (b == from) as i32;
+                let delta = delta_a * counts[b] as i32 + delta_b * counts[a] as i32;
+                dydt[a * NSTRATEGIES + b] += rate * delta as f64 / degree;
+            }
+        }
+    };
+
+    for from in 0..NSTRATEGIES {
+        if singlets[from] <= 0.0 {
+            continue;
+        }
+
+        for to in 0..NSTRATEGIES {
+            if to == from {
+                continue;
+            }
+
+            let probability_pair = state[from * NSTRATEGIES + to];
+            if probability_pair > 0.0 {
+                for (rest_focal, coefficient_focal) in compositions_rest {
+                    let probability_focal = composition_probability(
+                        rest_focal,
+                        *coefficient_focal,
+                        &conditionals[from],
+                    );
+                    if probability_focal <= 0.0 {
+                        continue;
+                    }
+
+                    let mut counts_focal = *rest_focal;
+                    counts_focal[to] += 1;
+                    let payoff_focal = payoff_local(
+                        from,
+                        &counts_focal,
+                        &fighters_defender,
+                        resources,
+                        pars_model,
+                    );
+
+                    for (rest_model, coefficient_model) in compositions_rest {
+                        let probability_model = composition_probability(
+                            rest_model,
+                            *coefficient_model,
+                            &conditionals[to],
+                        );
+                        if probability_model <= 0.0 {
+                            continue;
+                        }
+
+                        let mut counts_model = *rest_model;
+                        counts_model[from] += 1;
+                        let payoff_model = payoff_local(
+                            to,
+                            &counts_model,
+                            &fighters_defender,
+                            resources,
+                            pars_model,
+                        );
+
+                        let rate = probability_pair
+                            * probability_focal
+                            * probability_model
+                            * fermi(payoff_focal, payoff_model, pars_model.parameter_noise);
+                        apply_switch(from, to, &counts_focal, rate);
+                    }
+                }
+            }
+
+            if pars_model.rate_mutation > 0.0 {
+                for (counts_focal, coefficient_focal) in compositions_full {
+                    let rate = singlets[from]
+                        * composition_probability(
+                            counts_focal,
+                            *coefficient_focal,
+                            &conditionals[from],
+                        )
+                        * pars_model.rate_mutation
+                        / NSTRATEGIES as f64;
+                    apply_switch(from, to, counts_focal, rate);
+                }
+            }
+        }
+    }
+
+    let payoffs = pair_approximation_payoffs(state, compositions_full, pars_model);
+    let payoff_mean: f64 = (0..NSTRATEGIES).map(|s| singlets[s] * payoffs[s]).sum();
+
+    dydt[INDEX_RESOURCES] =
+        (1.0 - pars_model.rate_consumption) * payoff_mean - pars_model.rate_consumption * resources;
+}
+
+/// Expected one-step payoff of each strategy, averaging over neighborhoods drawn from the
+/// conditional pair probabilities.
+pub fn pair_approximation_payoffs(
+    state: &[f64],
+    compositions_full: &Compositions,
+    pars_model: &Input,
+) -> [f64; NSTRATEGIES] {
+    let degree = pars_model.degree_average.round();
+    let singlets = singlets(state);
+    let conditionals = conditionals(state, &singlets);
+    let fighters_defender = fighters_defender(&conditionals, degree);
+
+    let mut payoffs = [0.0; NSTRATEGIES];
+    for (strategy, payoff) in payoffs.iter_mut().enumerate() {
+        for (counts, coefficient) in compositions_full {
+            *payoff += composition_probability(counts, *coefficient, &conditionals[strategy])
+                * payoff_local(
+                    strategy,
+                    counts,
+                    &fighters_defender,
+                    state[INDEX_RESOURCES],
+                    pars_model,
+                );
+        }
+    }
+
+    payoffs
+}
+
+/// One-step payoff of an agent with the given neighbor counts. Fighters split their war
+/// resources over all edges, defenders over the fighters around them. A fighter's target is
+/// assumed to face the focal fighter plus the expected number of further fighters.
+fn payoff_local(
+    strategy: usize,
+    counts: &[usize; NSTRATEGIES],
+    fighters_defender: &[f64; NSTRATEGIES],
+    resources: f64,
+    pars_model: &Input,
+) -> f64 {
+    let war_resources = pars_model.fraction_investment * resources;
+    let attacker_war_resources = war_resources / pars_model.degree_average.round();
+
+    match strategy {
+        INDEX_FIGHTER => [INDEX_COOPERATOR, INDEX_DEFECTOR]
+            .iter()
+            .map(|target| {
+                counts[*target] as f64
+                    * expected_gain_fight(
+                        attacker_war_resources,
+                        war_resources / fighters_defender[*target],
+                        pars_model,
+                    )
+            })
+            .sum(),
+        _ => {
+            let payoff_game = if strategy == INDEX_COOPERATOR {
+                pars_model.payoff_cooperation
+            } else {
+                pars_model.payoff_defection
+            };
+            let nfighters = counts[INDEX_FIGHTER] as f64;
+            let loss_fight = if nfighters > 0.0 {
+                nfighters
+                    * expected_gain_fight(
+                        attacker_war_resources,
+                        war_resources / nfighters,
+                        pars_model,
+                    )
+            } else {
+                0.0
+            };
+
+            counts[INDEX_COOPERATOR] as f64 * payoff_game - loss_fight
+        }
+    }
+}
+
+fn compositions(total: usize) -> Compositions {
+    let factorial = |n: usize| (1..=n).map(|i| i as f64).product::<f64>();
+
+    let mut compositions = Vec::new();
+    for ncooperators in 0..=total {
+        for ndefectors in 0..=(total - ncooperators) {
+            let nfighters = total - ncooperators - ndefectors;
+            let coefficient = factorial(total)
+                / (factorial(ncooperators) * factorial(ndefectors) * factorial(nfighters));
+            compositions.push(([ncooperators, ndefectors, nfighters], coefficient));
+        }
+    }
+
+    compositions
+}
+
+fn composition_probability(
+    counts: &[usize; NSTRATEGIES],
+    coefficient: f64,
+    conditional: &[f64; NSTRATEGIES],
+) -> f64 {
+    let mut probability = coefficient;
+    for s in 0..NSTRATEGIES {
+        probability *= conditional[s].powi(counts[s] as i32);
+    }
+    probability
+}
+
+fn conditionals(state: &[f64], singlets: &[f64; NSTRATEGIES]) -> [[f64; NSTRATEGIES]; NSTRATEGIES] {
+    let mut conditionals = [[0.0; NSTRATEGIES]; NSTRATEGIES];
+    for a in 0..NSTRATEGIES {
+        if singlets[a] > 0.0 {
+            for b in 0..NSTRATEGIES {
+                conditionals[a][b] = (state[a * NSTRATEGIES + b] / singlets[a]).max(0.0);
+            }
+        }
+    }
+    conditionals
+}
+
+fn fighters_defender(
+    conditionals: &[[f64; NSTRATEGIES]; NSTRATEGIES],
+    degree: f64,
+) -> [f64; NSTRATEGIES] {
+    let mut fighters = [1.0; NSTRATEGIES];
+    for (strategy, nfighters) in fighters.iter_mut().enumerate() {
+        *nfighters += (degree - 1.0) * conditionals[strategy][INDEX_FIGHTER];
+    }
+    fighters
+}
+
+fn singlets(state: &[f64]) -> [f64; NSTRATEGIES] {
+    let mut singlets = [0.0; NSTRATEGIES];
+    for (a, singlet) in singlets.iter_mut().enumerate() {
+        *singlet = (0..NSTRATEGIES).map(|b| state[a * NSTRATEGIES + b]).sum();
+    }
+    singlets
+}
@@ -1,15 +1,19 @@
 use csv::Writer;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     error::Error,
     fs::{File, OpenOptions},
-    io::{Read, Write},
+    io::{BufRead, BufReader, Read, Write},
     path::{Path, PathBuf},
 };
 use uuid::Uuid;
 
-use crate::agent::{ResourceDistributionModel, Strategy};
+use crate::agent::{
+    ConquestModel, ContestModel, EngineModel, FightModel, FitnessModel, NetworkModel,
+    ReciprocityModel, ReputationModel, ResourceDistributionModel, StateModel, Strategy,
+    TributeModel, ZealotModel,
+};
 
 #[derive(Serialize, Deserialize, Clone, Copy)]
 pub struct FightingEvent {
@@ -18,6 +22,8 @@ pub struct FightingEvent {
     pub id_focal: usize,
     pub investment_enemy: f64,
     pub investment_focal: f64,
+    pub military_stock_enemy: f64,
+    pub military_stock_focal: f64,
     pub resources_enemy: f64,
     pub resources_focal: f64,
     pub strategy_enemy: Strategy,
@@ -26,31 +32,272 @@ pub struct FightingEvent {
     pub winner: usize,
 }
 
+/// State of one agent at the end of a run.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct AgentSnapshot {
+    pub degree: usize,
+    pub flag_payer: bool,
+    pub id: usize,
+    pub image_score: i32,
+    pub military_stock: f64,
+    pub resources_cumulative: f64,
+    pub strategy: Strategy,
+    pub technology: f64,
+    pub territory: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct ContestParticipant {
+    pub id: usize,
+    pub investment: f64,
+    pub resources: f64,
+    pub strategy: Strategy,
+}
+
+/// Multi-party contest hosted by `id_target`; the target is the first participant.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ContestEvent {
+    pub id_event: usize,
+    pub id_target: usize,
+    pub id_winner: usize,
+    pub participants: Vec<ContestParticipant>,
+    pub time: usize,
+}
+
+/// Tribute demanded by a fighter from a non-fighter neighbor; a refusal leads to a fight.
 #[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct TributeEvent {
+    pub amount: f64,
+    pub flag_paid: bool,
+    pub id_event: usize,
+    pub id_extorter: usize,
+    pub id_victim: usize,
+    pub time: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Input {
     pub cutoff_resources: f64,
+    pub degree_average: f64,
+    pub flag_analysis_agent: bool,
     pub flag_analysis_event: bool,
     pub flag_analysis_global: bool,
     pub flag_analysis_time: bool,
+    pub flag_deterrence: bool,
+    pub flag_directed: bool,
+    pub flag_military_stock: bool,
+    pub flag_mixed: bool,
+    pub flag_technology: bool,
+    pub flag_temporal: bool,
+    pub flag_temporal_cycle: bool,
+    pub flag_weight_fight: bool,
+    pub flag_weight_imitation: bool,
+    pub flag_weight_payoff: bool,
     pub fraction_cooperators: f64,
     pub fraction_defectors: f64,
+    pub fraction_destruction: f64,
     pub fraction_investment: f64,
+    pub fraction_payers: f64,
+    pub fraction_plunder: f64,
+    pub fraction_tribute: f64,
+    pub fraction_zealots: f64,
+    pub model_conquest: ConquestModel,
+    pub model_contest: ContestModel,
     pub model_distribution_resources: ResourceDistributionModel,
+    pub model_engine: EngineModel,
+    pub model_fight: FightModel,
+    pub model_fitness: FitnessModel,
+    pub model_network: NetworkModel,
+    pub model_reciprocity: ReciprocityModel,
+    pub model_reputation: ReputationModel,
+    pub model_state: StateModel,
+    pub model_tribute: TributeModel,
+    pub model_zealots: ZealotModel,
     pub nsims: usize,
+    pub number_rounds: usize,
+    pub parameter_mutation: f64,
     pub parameter_noise: f64,
+    pub parameter_research: f64,
     pub parameter_technology: f64,
     pub payoff_cooperation: f64,
     pub payoff_defection: f64,
+    pub path_interventions: String,
+    pub path_schedules: String,
+    pub path_zealots: String,
+    pub probability_observation: f64,
+    pub probability_truce_breach: f64,
+    pub probability_update: f64,
+    pub rate_armament: f64,
+    pub rate_blend: f64,
     pub rate_consumption: f64,
+    pub rate_depreciation: f64,
+    pub rate_discount: f64,
+    pub rate_fight: f64,
+    pub rate_game: f64,
+    pub rate_imitation: f64,
+    pub rate_mutation: f64,
+    pub rate_research: f64,
+    pub rate_tax: f64,
+    pub size_population: usize,
+    pub size_sample_well_mixed: usize,
+    pub strategy_zealots: Strategy,
+    pub threshold_conquest: f64,
+    pub threshold_deterrence: f64,
     pub t_average: usize,
     pub t_equilibrium: usize,
+    pub t_snapshot: usize,
+    pub t_truce: usize,
+    pub t_window: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduledParameter {
+    FractionInvestment,
+    ParameterNoise,
+    ParameterTechnology,
+    PayoffDefection,
+    RateConsumption,
+}
+
+/// Time dependence of a scheduled parameter, relative to its constant `Input` value.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum Schedule {
+    /// Piecewise constant: each `(t, value)` holds from `t` until the next change.
+    Steps { changes: Vec<(usize, f64)> },
+    /// Piecewise linear through the `(t, value)` points, held constant after the last one.
+    Linear { points: Vec<(usize, f64)> },
+    /// Sinusoidal forcing around the constant value.
+    Periodic {
+        amplitude: f64,
+        period: f64,
+        phase: f64,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ParameterSchedule {
+    pub parameter: ScheduledParameter,
+    pub schedule: Schedule,
+}
+
+impl ScheduledParameter {
+    pub fn value(&self, pars_model: &Input) -> f64 {
+        match self {
+            ScheduledParameter::FractionInvestment => pars_model.fraction_investment,
+            ScheduledParameter::ParameterNoise => pars_model.parameter_noise,
+            ScheduledParameter::ParameterTechnology => pars_model.parameter_technology,
+            ScheduledParameter::PayoffDefection => pars_model.payoff_defection,
+            ScheduledParameter::RateConsumption => pars_model.rate_consumption,
+        }
+    }
+
+    pub fn value_mut<'a>(&self, pars_model: &'a mut Input) -> &'a mut f64 {
+        match self {
+            ScheduledParameter::FractionInvestment => &mut pars_model.fraction_investment,
+            ScheduledParameter::ParameterNoise => &mut pars_model.parameter_noise,
+            ScheduledParameter::ParameterTechnology => &mut pars_model.parameter_technology,
+            ScheduledParameter::PayoffDefection => &mut pars_model.payoff_defection,
+            ScheduledParameter::RateConsumption => &mut pars_model.rate_consumption,
+        }
+    }
+}
+
+impl Schedule {
+    pub fn value_at(&self, t: usize, value: f64) -> f64 {
+        match self {
+            Schedule::Steps { changes } => changes
+                .iter()
+                .rev()
+                .find(|(t_change, _)| *t_change <= t)
+                .map_or(value, |(_, value_change)| *value_change),
+            Schedule::Linear { points } => {
+                match points.iter().position(|(t_point, _)| *t_point > t) {
+                    Some(0) => value,
+                    Some(i) => {
+                        let (t_start, value_start) = points[i - 1];
+                        let (t_end, value_end) = points[i];
+                        value_start
+                            + (value_end - value_start) * (t - t_start) as f64
+                                / (t_end - t_start) as f64
+                    }
+                    None => points.last().map_or(value, |(_, value_last)| *value_last),
+                }
+            }
+            Schedule::Periodic {
+                amplitude,
+                period,
+                phase,
+            } => {
+                value + amplitude * f64::sin(2.0 * std::f64::consts::PI * t as f64 / period + phase)
+            }
+        }
+    }
+}
+
+/// Sets the scheduled parameters of `pars_current` to their values at time `t`. Schedules of the
+/// same parameter are composed in file order, each acting on the value left by the previous one.
+/// Only the discrete-time engine applies schedules, once per time step.
+pub fn apply_schedules(
+    pars_current: &mut Input,
+    pars_base: &Input,
+    schedules: &[ParameterSchedule],
+    t: usize,
+) {
+    for parameter_schedule in schedules {
+        let parameter = parameter_schedule.parameter;
+        *parameter.value_mut(pars_current) = parameter.value(pars_base);
+    }
+
+    for parameter_schedule in schedules {
+        let value = parameter_schedule.parameter.value_mut(pars_current);
+        *value = parameter_schedule.schedule.value_at(t, *value);
+    }
+}
+
+/// Scripted change to the population at a given time.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum InterventionAction {
+    /// Adds `amount` to the resources of every agent playing `strategy`.
+    Aid { strategy: Strategy, amount: f64 },
+    /// Switches the listed agents playing `strategy_old` (any, if absent) to `strategy_new`.
+    /// Zealots and unknown ids are left untouched.
+    Convert {
+        ids: Vec<usize>,
+        strategy_old: Option<Strategy>,
+        strategy_new: Strategy,
+    },
+    /// Sets the investment in fights to zero for `duration` time steps, which also keeps any
+    /// military stock out of fights.
+    Disarm { duration: usize },
+    /// Cuts every edge of the `number` richest fighters.
+    IsolateRichestFighters { number: usize },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Intervention {
+    pub t: usize,
+    pub action: InterventionAction,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OutputMetadata {
+    pub input: Input,
+    pub interventions: Vec<Intervention>,
+    pub schedules: Vec<ParameterSchedule>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Output {
     pub global: OutputGlobal,
+    pub agents: Option<Vec<AgentSnapshot>>,
+    pub contests: Option<Vec<ContestEvent>>,
     pub events: Option<Vec<FightingEvent>>,
+    pub territory: Option<Vec<usize>>,
     pub time: Option<TimeSeries>,
+    pub tributes: Option<Vec<TributeEvent>>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Copy)]
@@ -82,6 +329,9 @@ struct OutputGlobalToCsv {
     payoff_defectors: f64,
     payoff_fighters: f64,
     fraction_investment: f64,
+    model_engine: EngineModel,
+    model_fight: FightModel,
+    model_network: NetworkModel,
     parameter_noise: f64,
     parameter_technology: f64,
     payoff_defection: f64,
@@ -97,6 +347,71 @@ pub struct TimeSeries {
     pub payoff_cooperators: Vec<f64>,
     pub payoff_defectors: Vec<f64>,
     pub payoff_fighters: Vec<f64>,
+    pub snapshot: Vec<usize>,
+    pub number_zealots: Vec<usize>,
+    pub payoff_zealots: Vec<f64>,
+    pub intervention: Vec<bool>,
+    pub military_stock: Vec<f64>,
+    pub number_deterred: Vec<usize>,
+    pub number_truces: Vec<usize>,
+    pub state_pool: Vec<f64>,
+    pub state_spending: Vec<f64>,
+    pub simplex: Vec<[f64; 3]>,
+    pub technology: Vec<f64>,
+    pub technology_dispersion: Vec<f64>,
+}
+
+impl TimeSeries {
+    pub fn new(t_total: usize) -> Self {
+        Self {
+            number_cooperators: vec![0; t_total],
+            number_defectors: vec![0; t_total],
+            number_fighters: vec![0; t_total],
+            payoff_cooperators: vec![0.0; t_total],
+            payoff_defectors: vec![0.0; t_total],
+            payoff_fighters: vec![0.0; t_total],
+            snapshot: vec![0; t_total],
+            number_zealots: vec![0; t_total],
+            payoff_zealots: vec![0.0; t_total],
+            intervention: vec![false; t_total],
+            military_stock: vec![0.0; t_total],
+            number_deterred: vec![0; t_total],
+            number_truces: vec![0; t_total],
+            state_pool: vec![0.0; t_total],
+            state_spending: vec![0.0; t_total],
+            simplex: vec![[0.0; 3]; t_total],
+            technology: vec![0.0; t_total],
+            technology_dispersion: vec![0.0; t_total],
+        }
+    }
+}
+
+pub fn assemble_agents(output_ensemble: &[Output]) -> Vec<&Vec<AgentSnapshot>> {
+    output_ensemble
+        .iter()
+        .filter_map(|output| output.agents.as_ref())
+        .collect()
+}
+
+pub fn assemble_contests(output_ensemble: &[Output]) -> Vec<&Vec<ContestEvent>> {
+    output_ensemble
+        .iter()
+        .filter_map(|output| output.contests.as_ref())
+        .collect()
+}
+
+pub fn assemble_territory(output_ensemble: &[Output]) -> Vec<&Vec<usize>> {
+    output_ensemble
+        .iter()
+        .filter_map(|output| output.territory.as_ref())
+        .collect()
+}
+
+pub fn assemble_tributes(output_ensemble: &[Output]) -> Vec<&Vec<TributeEvent>> {
+    output_ensemble
+        .iter()
+        .filter_map(|output| output.tributes.as_ref())
+        .collect()
 }
 
 pub fn assemble_events(output_ensemble: &Vec<Output>) -> Vec<&Vec<FightingEvent>> {
@@ -141,11 +456,14 @@ pub fn assemble_global(output_ensemble: &Vec<Output>) -> OutputGlobalAssembled {
 
 pub fn construct_string_game(pars_input: &Input) -> String {
     format!(
-        "fc{}_fd{}_fi{}_mdr{}_ns{}_noi{}_tec{}_pd{}_rc{}_ta{}_te{}",
+        "fc{}_fd{}_fi{}_mdr{}_me{}_mf{}_mn{}_ns{}_noi{}_tec{}_pd{}_rc{}_ta{}_te{}",
         pars_input.fraction_cooperators,
         pars_input.fraction_defectors,
         pars_input.fraction_investment,
         pars_input.model_distribution_resources,
+        pars_input.model_engine,
+        pars_input.model_fight,
+        pars_input.model_network,
         pars_input.nsims,
         pars_input.parameter_noise,
         pars_input.parameter_technology,
@@ -181,24 +499,304 @@ pub fn convert_string_to_location_seed_enum(
 }
 
 pub fn get_string_network(path_network: &Path) -> Option<String> {
-    let file_name = path_network.file_name()?.to_str()?;
-
-    let string_network = file_name.strip_suffix(".json")?;
+    let string_network = path_network.file_stem()?.to_str()?;
 
     Some(string_network.to_string())
 }
 
-pub fn load_network(path_network: &PathBuf) -> HashMap<usize, Vec<usize>> {
-    let mut file = File::open(path_network).expect("Failed to open file");
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NeighborEntry {
+    Unweighted(usize),
+    Weighted(usize, f64),
+}
+
+pub fn load_network(
+    path_network: &PathBuf,
+    flag_directed: bool,
+) -> HashMap<usize, Vec<(usize, f64)>> {
+    match path_network
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("json") => load_network_adjacency_list(path_network),
+        _ => load_network_edge_list(path_network, flag_directed),
+    }
+}
+
+/// Reads a JSON adjacency list. Neighbors are either plain ids or `[id, weight]` pairs;
+/// plain ids get unit weight. In directed mode each list holds the out-neighbors of the node.
+pub fn load_network_adjacency_list(path_network: &PathBuf) -> HashMap<usize, Vec<(usize, f64)>> {
+    let content = read_file(path_network);
+
+    let raw_list: HashMap<usize, Vec<NeighborEntry>> =
+        serde_json::from_str(&content).expect("Failed to deserialize JSON");
+
+    convert_neighbor_entries(raw_list)
+}
+
+/// Reads an edge list with one `source target [weight]` edge per line, separated by whitespace
+/// or commas. Lines starting with `#` are skipped and missing weights default to 1. Edges are
+/// symmetrized unless the network is directed.
+pub fn load_network_edge_list(
+    path_network: &PathBuf,
+    flag_directed: bool,
+) -> HashMap<usize, Vec<(usize, f64)>> {
+    let file = File::open(path_network).expect("Failed to open file");
+    let reader = BufReader::new(file);
+
+    let mut adjacency_list: HashMap<usize, Vec<(usize, f64)>> = HashMap::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.expect("Failed to read file");
+        let fields = split_edge_line(&line);
+        if fields.is_empty() {
+            continue;
+        }
+
+        let Some((source, target, weight)) = parse_edge(&fields) else {
+            println!("Skipping malformed edge at line {}", index + 1);
+            continue;
+        };
+        insert_edge(&mut adjacency_list, source, target, weight, flag_directed);
+    }
+
+    let nagents = number_of_nodes(&adjacency_list);
+    fill_missing_nodes(&mut adjacency_list, nagents);
+
+    adjacency_list
+}
+
+/// Sequence of network snapshots, each active from its entry in `times` until the next one.
+#[derive(Clone)]
+pub struct TemporalNetwork {
+    pub snapshots: Vec<HashMap<usize, Vec<(usize, f64)>>>,
+    pub times: Vec<usize>,
+}
+
+impl TemporalNetwork {
+    pub fn number_of_snapshots(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Index of the snapshot active at step `t`. When cycling, the sequence restarts once the
+    /// last snapshot has been active for `t_snapshot` steps.
+    pub fn snapshot_at(&self, t: usize, t_snapshot: usize, flag_cycle: bool) -> usize {
+        let t = if flag_cycle {
+            t % (self.times.last().unwrap() + t_snapshot.max(1))
+        } else {
+            t
+        };
+
+        self.times.iter().rposition(|time| *time <= t).unwrap_or(0)
+    }
+}
+
+pub fn load_network_temporal(
+    path_network: &PathBuf,
+    flag_directed: bool,
+    t_snapshot: usize,
+) -> TemporalNetwork {
+    let mut temporal_network = match path_network
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("json") => load_network_temporal_adjacency_list(path_network, t_snapshot),
+        _ => load_network_temporal_edge_list(path_network, flag_directed),
+    };
+
+    let nagents = temporal_network
+        .snapshots
+        .iter()
+        .map(number_of_nodes)
+        .max()
+        .unwrap_or(0);
+    for snapshot in temporal_network.snapshots.iter_mut() {
+        fill_missing_nodes(snapshot, nagents);
+    }
+
+    temporal_network
+}
+
+/// Reads a JSON array of adjacency lists. Snapshot `i` becomes active at step `i * t_snapshot`.
+pub fn load_network_temporal_adjacency_list(
+    path_network: &PathBuf,
+    t_snapshot: usize,
+) -> TemporalNetwork {
+    let content = read_file(path_network);
+
+    let raw_snapshots: Vec<HashMap<usize, Vec<NeighborEntry>>> =
+        serde_json::from_str(&content).expect("Failed to deserialize JSON");
+
+    let times = (0..raw_snapshots.len()).map(|i| i * t_snapshot).collect();
+    let snapshots = raw_snapshots
+        .into_iter()
+        .map(convert_neighbor_entries)
+        .collect();
+
+    TemporalNetwork { snapshots, times }
+}
+
+/// Reads a timestamped edge list with one `time source target [weight]` edge per line. Every
+/// distinct time opens a new snapshot made of the edges listed with that time.
+pub fn load_network_temporal_edge_list(
+    path_network: &PathBuf,
+    flag_directed: bool,
+) -> TemporalNetwork {
+    let file = File::open(path_network).expect("Failed to open file");
+    let reader = BufReader::new(file);
+
+    let mut snapshot_map: BTreeMap<usize, HashMap<usize, Vec<(usize, f64)>>> = BTreeMap::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line.expect("Failed to read file");
+        let fields = split_edge_line(&line);
+        if fields.is_empty() {
+            continue;
+        }
+
+        let time: usize = fields[0].parse().expect("Failed to parse edge time");
+        let Some((source, target, weight)) = parse_edge(&fields[1..]) else {
+            println!("Skipping malformed edge at line {}", index + 1);
+            continue;
+        };
+        insert_edge(
+            snapshot_map.entry(time).or_default(),
+            source,
+            target,
+            weight,
+            flag_directed,
+        );
+    }
+
+    let (times, snapshots) = snapshot_map.into_iter().unzip();
+
+    TemporalNetwork { snapshots, times }
+}
+
+fn convert_neighbor_entries(
+    raw_list: HashMap<usize, Vec<NeighborEntry>>,
+) -> HashMap<usize, Vec<(usize, f64)>> {
+    let mut adjacency_list = HashMap::new();
+    for (node, entries) in raw_list {
+        let neighbors = entries
+            .into_iter()
+            .map(|entry| match entry {
+                NeighborEntry::Unweighted(neighbor) => (neighbor, 1.0),
+                NeighborEntry::Weighted(neighbor, weight) => (neighbor, weight),
+            })
+            .collect();
+        adjacency_list.insert(node, neighbors);
+    }
+
+    adjacency_list
+}
+
+fn fill_missing_nodes(adjacency_list: &mut HashMap<usize, Vec<(usize, f64)>>, nagents: usize) {
+    for node in 0..nagents {
+        adjacency_list.entry(node).or_default();
+    }
+}
+
+fn insert_edge(
+    adjacency_list: &mut HashMap<usize, Vec<(usize, f64)>>,
+    source: usize,
+    target: usize,
+    weight: f64,
+    flag_directed: bool,
+) {
+    adjacency_list
+        .entry(source)
+        .or_default()
+        .push((target, weight));
+    if flag_directed {
+        adjacency_list.entry(target).or_default();
+    } else {
+        adjacency_list
+            .entry(target)
+            .or_default()
+            .push((source, weight));
+    }
+}
+
+fn number_of_nodes(adjacency_list: &HashMap<usize, Vec<(usize, f64)>>) -> usize {
+    adjacency_list
+        .iter()
+        .flat_map(|(node, neighbors)| {
+            std::iter::once(*node).chain(neighbors.iter().map(|(neighbor, _)| *neighbor))
+        })
+        .max()
+        .map_or(0, |max_node| max_node + 1)
+}
+
+/// Parses `source target [weight]`; returns `None` when the target is missing.
+fn parse_edge(fields: &[&str]) -> Option<(usize, usize, f64)> {
+    if fields.len() < 2 {
+        return None;
+    }
+
+    let source: usize = fields[0].parse().expect("Failed to parse edge source");
+    let target: usize = fields[1].parse().expect("Failed to parse edge target");
+    let weight: f64 = match fields.get(2) {
+        Some(field) => field.parse().expect("Failed to parse edge weight"),
+        None => 1.0,
+    };
+
+    Some((source, target, weight))
+}
+
+fn read_file(path: &PathBuf) -> String {
+    let mut file = File::open(path).expect("Failed to open file");
 
     let mut content = String::new();
     file.read_to_string(&mut content)
         .expect("Failed to read file");
 
-    let adjcency_list: HashMap<usize, Vec<usize>> =
+    content
+}
+
+fn split_edge_line(line: &str) -> Vec<&str> {
+    let line = line.trim();
+    if line.starts_with('#') {
+        return Vec::new();
+    }
+
+    line.split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|field| !field.is_empty())
+        .collect()
+}
+
+/// Reads a JSON array of parameter schedules, sorting step changes and linear points by time.
+pub fn load_schedules(path: &PathBuf) -> Vec<ParameterSchedule> {
+    let content = read_file(path);
+
+    let mut schedules: Vec<ParameterSchedule> =
         serde_json::from_str(&content).expect("Failed to deserialize JSON");
+    for parameter_schedule in schedules.iter_mut() {
+        match &mut parameter_schedule.schedule {
+            Schedule::Steps { changes } => changes.sort_by_key(|(t, _)| *t),
+            Schedule::Linear { points } => points.sort_by_key(|(t, _)| *t),
+            Schedule::Periodic { period, .. } => {
+                assert!(*period > 0.0, "Schedule period must be positive");
+            }
+        }
+    }
 
-    adjcency_list
+    schedules
+}
+
+/// Reads a JSON array of interventions.
+pub fn load_interventions(path: &PathBuf) -> Vec<Intervention> {
+    let content = read_file(path);
+
+    serde_json::from_str(&content).expect("Failed to deserialize JSON")
+}
+
+/// Reads a JSON array of node ids.
+pub fn load_node_list(path: &PathBuf) -> Vec<usize> {
+    let content = read_file(path);
+
+    serde_json::from_str(&content).expect("Failed to deserialize JSON")
 }
 
 pub fn save_to_json<T: Serialize>(data: &T, path: &PathBuf) -> Result<(), Box<dyn Error>> {
@@ -230,6 +828,9 @@ pub fn save_global_results(
             "payoff_defectors",
             "payoff_fighters",
             "fraction_investment",
+            "model_engine",
+            "model_fight",
+            "model_network",
             "parameter_noise",
             "parameter_technology",
             "payoff_defection",
@@ -244,6 +845,9 @@ pub fn save_global_results(
         fraction_defectors: output_global.fraction_defectors,
         fraction_fighters: output_global.fraction_fighters,
         fraction_investment: pars_input.fraction_investment,
+        model_engine: pars_input.model_engine,
+        model_fight: pars_input.model_fight,
+        model_network: pars_input.model_network,
         payoff_cooperators: output_global.payoff_cooperators,
         payoff_defectors: output_global.payoff_defectors,
         payoff_fighters: output_global.payoff_fighters,
@@ -273,6 +877,18 @@ pub fn summary_stats_output(output_ensemble: &Vec<Output>) -> Output {
     let mut avg_time_payoff_cooperators: Vec<f64> = Vec::new();
     let mut avg_time_payoff_defectors: Vec<f64> = Vec::new();
     let mut avg_time_payoff_fighters: Vec<f64> = Vec::new();
+    let mut time_snapshot: Vec<usize> = Vec::new();
+    let mut time_intervention: Vec<bool> = Vec::new();
+    let mut avg_time_number_zealots: Vec<usize> = Vec::new();
+    let mut avg_time_payoff_zealots: Vec<f64> = Vec::new();
+    let mut avg_time_military_stock: Vec<f64> = Vec::new();
+    let mut avg_time_number_deterred: Vec<usize> = Vec::new();
+    let mut avg_time_number_truces: Vec<usize> = Vec::new();
+    let mut avg_time_state_pool: Vec<f64> = Vec::new();
+    let mut avg_time_state_spending: Vec<f64> = Vec::new();
+    let mut avg_time_simplex: Vec<[f64; 3]> = Vec::new();
+    let mut avg_time_technology: Vec<f64> = Vec::new();
+    let mut avg_time_technology_dispersion: Vec<f64> = Vec::new();
 
     let nsims = output_ensemble.len();
 
@@ -292,6 +908,18 @@ pub fn summary_stats_output(output_ensemble: &Vec<Output>) -> Output {
                 avg_time_payoff_cooperators.resize(time.payoff_cooperators.len(), 0.0);
                 avg_time_payoff_defectors.resize(time.payoff_defectors.len(), 0.0);
                 avg_time_payoff_fighters.resize(time.payoff_fighters.len(), 0.0);
+                time_snapshot = time.snapshot.clone();
+                time_intervention = time.intervention.clone();
+                avg_time_number_zealots.resize(time.number_zealots.len(), 0);
+                avg_time_payoff_zealots.resize(time.payoff_zealots.len(), 0.0);
+                avg_time_military_stock.resize(time.military_stock.len(), 0.0);
+                avg_time_number_deterred.resize(time.number_deterred.len(), 0);
+                avg_time_number_truces.resize(time.number_truces.len(), 0);
+                avg_time_state_pool.resize(time.state_pool.len(), 0.0);
+                avg_time_state_spending.resize(time.state_spending.len(), 0.0);
+                avg_time_simplex.resize(time.simplex.len(), [0.0; 3]);
+                avg_time_technology.resize(time.technology.len(), 0.0);
+                avg_time_technology_dispersion.resize(time.technology_dispersion.len(), 0.0);
             }
 
             for i in 0..time.number_cooperators.len() {
@@ -301,6 +929,18 @@ pub fn summary_stats_output(output_ensemble: &Vec<Output>) -> Output {
                 avg_time_payoff_cooperators[i] += time.payoff_cooperators[i];
                 avg_time_payoff_defectors[i] += time.payoff_defectors[i];
                 avg_time_payoff_fighters[i] += time.payoff_fighters[i];
+                avg_time_number_zealots[i] += time.number_zealots[i];
+                avg_time_payoff_zealots[i] += time.payoff_zealots[i];
+                avg_time_military_stock[i] += time.military_stock[i];
+                avg_time_number_deterred[i] += time.number_deterred[i];
+                avg_time_number_truces[i] += time.number_truces[i];
+                avg_time_state_pool[i] += time.state_pool[i];
+                avg_time_state_spending[i] += time.state_spending[i];
+                avg_time_technology[i] += time.technology[i];
+                avg_time_technology_dispersion[i] += time.technology_dispersion[i];
+                for (average, value) in avg_time_simplex[i].iter_mut().zip(time.simplex[i]) {
+                    *average += value;
+                }
             }
         }
     }
@@ -319,6 +959,18 @@ pub fn summary_stats_output(output_ensemble: &Vec<Output>) -> Output {
         avg_time_payoff_cooperators[i] /= nsims as f64;
         avg_time_payoff_defectors[i] /= nsims as f64;
         avg_time_payoff_fighters[i] /= nsims as f64;
+        avg_time_number_zealots[i] /= nsims;
+        avg_time_payoff_zealots[i] /= nsims as f64;
+        avg_time_military_stock[i] /= nsims as f64;
+        avg_time_number_deterred[i] /= nsims;
+        avg_time_number_truces[i] /= nsims;
+        avg_time_state_pool[i] /= nsims as f64;
+        avg_time_state_spending[i] /= nsims as f64;
+        avg_time_technology[i] /= nsims as f64;
+        avg_time_technology_dispersion[i] /= nsims as f64;
+        avg_time_simplex[i]
+            .iter_mut()
+            .for_each(|average| *average /= nsims as f64);
     }
 
     let output_global = OutputGlobal {
@@ -337,11 +989,89 @@ pub fn summary_stats_output(output_ensemble: &Vec<Output>) -> Output {
         payoff_cooperators: avg_time_payoff_cooperators,
         payoff_defectors: avg_time_payoff_defectors,
         payoff_fighters: avg_time_payoff_fighters,
+        snapshot: time_snapshot,
+        number_zealots: avg_time_number_zealots,
+        payoff_zealots: avg_time_payoff_zealots,
+        intervention: time_intervention,
+        military_stock: avg_time_military_stock,
+        number_deterred: avg_time_number_deterred,
+        number_truces: avg_time_number_truces,
+        state_pool: avg_time_state_pool,
+        state_spending: avg_time_state_spending,
+        simplex: avg_time_simplex,
+        technology: avg_time_technology,
+        technology_dispersion: avg_time_technology_dispersion,
     };
 
     Output {
         global: output_global,
+        agents: None,
+        contests: None,
         events: None,
+        territory: None,
+        tributes: None,
         time: Some(output_time),
     }
 }
+
+/// Rejects flag combinations that one of the engines would otherwise ignore silently.
+pub fn validate_input(pars_input: &Input) -> Result<(), String> {
+    if pars_input.flag_temporal
+        && matches!(
+            pars_input.model_network,
+            NetworkModel::Annealed | NetworkModel::AnnealedMixed
+        )
+    {
+        return Err("annealed rewiring cannot be combined with temporal networks".to_owned());
+    }
+    if pars_input.model_engine == EngineModel::PairApproximation
+        && pars_input.degree_average.round() < 1.0
+    {
+        return Err("the pair approximation needs degree_average >= 1".to_owned());
+    }
+    if pars_input.model_engine != EngineModel::Agent
+        && !(pars_input.path_schedules.is_empty() && pars_input.path_interventions.is_empty())
+    {
+        return Err("schedules and interventions need the agent engine".to_owned());
+    }
+    if pars_input.model_engine == EngineModel::Gillespie
+        && (pars_input.flag_temporal || pars_input.model_network != NetworkModel::Quenched)
+    {
+        return Err("the Gillespie engine needs a static quenched network".to_owned());
+    }
+    if pars_input.model_engine == EngineModel::Gillespie && pars_input.flag_mixed {
+        return Err("mixed strategies need the agent engine".to_owned());
+    }
+    if pars_input.model_engine == EngineModel::Gillespie
+        && pars_input.model_fitness != FitnessModel::Cumulative
+    {
+        return Err("the Gillespie engine only supports cumulative fitness".to_owned());
+    }
+    if pars_input.model_contest == ContestModel::Multiparty
+        && (pars_input.model_engine == EngineModel::Gillespie
+            || pars_input.model_network == NetworkModel::WellMixed)
+    {
+        return Err("multiparty contests need the agent engine on a network".to_owned());
+    }
+    if pars_input.model_conquest != ConquestModel::None
+        && (pars_input.model_engine == EngineModel::Gillespie
+            || pars_input.model_network == NetworkModel::WellMixed)
+    {
+        return Err("conquest needs the agent engine on a network".to_owned());
+    }
+    if pars_input.model_conquest == ConquestModel::Edge
+        && (!pars_input.flag_directed
+            || pars_input.flag_temporal
+            || pars_input.model_network != NetworkModel::Quenched)
+    {
+        return Err("edge conquest needs a static, quenched and directed network".to_owned());
+    }
+    if pars_input.model_tribute != TributeModel::None
+        && (pars_input.model_contest == ContestModel::Multiparty
+            || pars_input.model_network == NetworkModel::WellMixed)
+    {
+        return Err("tribute demands need pairwise fights on a network".to_owned());
+    }
+
+    Ok(())
+}